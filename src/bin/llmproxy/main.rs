@@ -1,115 +1,410 @@
-use clap::{Parser, Subcommand};
+use clap::{ArgGroup, Parser, Subcommand};
 use colored::*;
-use llmproxy::client::Client;
+use llmproxy::client::{Client, ClientError};
+use llmproxy::models::{ListQuery, RegisterRequest};
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
-const BASE_URL: &str = "http://127.0.0.1:11450";
+const DEFAULT_BASE_URL: &str = "http://127.0.0.1:11450";
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+
+    /// URL of the llmproxyd instance to manage
+    #[arg(
+        long,
+        global = true,
+        env = "LLMPROXY_URL",
+        default_value = DEFAULT_BASE_URL,
+        value_parser = parse_base_url
+    )]
+    base_url: String,
+
+    /// Admin token for llmproxyd instances started with --admin-token
+    #[arg(long, global = true, env = "LLMPROXY_TOKEN", hide_env_values = true)]
+    token: Option<String>,
 }
 
 #[derive(Subcommand, Clone)]
 enum Commands {
     /// Register a new model service with the orchestrator
     Register {
-        #[arg(long, help = "Name of the model (e.g., Qwen/Qwen2-7B-Instruct)")]
-        model_name: String,
-        #[arg(long, help = "Address of the model service (e.g., localhost:8001)")]
-        addr: String,
+        #[arg(
+            long,
+            required = true,
+            help = "Name of the model (e.g., Qwen/Qwen2-7B-Instruct); repeat for services serving several models"
+        )]
+        model_name: Vec<String>,
+        #[arg(
+            long,
+            required = true,
+            help = "Address of the model service (e.g., localhost:8001); repeat to list fallbacks tried in order"
+        )]
+        addr: Vec<String>,
+        #[arg(long, help = "Treat the model name as a glob pattern (e.g., 'Qwen/*')")]
+        pattern: bool,
+        #[arg(long, help = "Relative share of the model's traffic (default: 1)")]
+        weight: Option<u32>,
+        #[arg(
+            long,
+            help = "Seconds to ignore failed health checks while the model loads"
+        )]
+        warmup_secs: Option<u64>,
+        #[arg(
+            long,
+            help = "Most requests the service handles at once; more go to other replicas"
+        )]
+        max_concurrency: Option<usize>,
+        #[arg(
+            long,
+            env = "LLMPROXY_UPSTREAM_API_KEY",
+            hide_env_values = true,
+            help = "API key the proxy sends to the service instead of the client's Authorization header"
+        )]
+        upstream_api_key: Option<String>,
+        #[arg(
+            long = "capability",
+            value_name = "CAPABILITY",
+            help = "Feature the service supports (e.g., vision), required by requests with X-Require-Capability; repeatable"
+        )]
+        capabilities: Vec<String>,
+    },
+    /// Register every model service listed in a JSON file in one request
+    RegisterBatch {
+        #[arg(
+            help = "JSON file with an array of registrations, each with the fields of POST /register"
+        )]
+        file: PathBuf,
     },
-    /// Unregister an existing model service by index number or address
+    /// Unregister model services by index number, address or model name, or all of them
+    #[command(group(ArgGroup::new("selector").required(true).args(["target", "addr", "model_name", "all"])))]
     Unregister {
+        #[arg(help = "Service index (e.g., 1, 2, 3) or address (e.g., localhost:8001)")]
+        target: Option<String>,
+        #[arg(long, help = "Address of the service to remove (e.g., localhost:8001)")]
+        addr: Option<String>,
+        #[arg(long, help = "Remove every service of this model")]
+        model_name: Option<String>,
+        #[arg(long, requires = "yes", help = "Remove every registered service")]
+        all: bool,
+        #[arg(long, help = "Confirm --all")]
+        yes: bool,
+    },
+    /// Stop sending new requests to a service while its requests in flight finish
+    Drain {
         #[arg(help = "Service index (e.g., 1, 2, 3) or address (e.g., localhost:8001)")]
         target: String,
     },
-    /// List all registered model services
-    List,
+    /// List registered model services
+    List {
+        #[arg(long, help = "Only list services of this model")]
+        model_name: Option<String>,
+        #[arg(long, help = "Skip this many services")]
+        offset: Option<usize>,
+        #[arg(long, help = "List at most this many services")]
+        limit: Option<usize>,
+    },
+    /// Show how many services of each model are registered and healthy
+    Status,
+    /// Wait until a model has enough healthy services, exiting non-zero on timeout
+    WaitReady {
+        #[arg(long, help = "Model that must be ready")]
+        model: String,
+        #[arg(
+            long,
+            default_value = "1",
+            help = "Number of healthy services required"
+        )]
+        min: usize,
+        #[arg(long, default_value = "300", help = "Seconds to wait before giving up")]
+        timeout: u64,
+    },
+    /// Send requests through the proxy and report latency percentiles and the error rate
+    Bench {
+        #[arg(long, help = "Model to send chat completions for")]
+        model: String,
+        #[arg(long, default_value = "100", help = "Number of requests to send")]
+        requests: usize,
+        #[arg(long, default_value = "10", help = "Requests in flight at once")]
+        concurrency: usize,
+    },
     /// Test a registered model service by ID
     Test {
         #[arg(help = "Service ID (e.g., 1, 2, 3) or address (e.g., localhost:8001)")]
         id: String,
     },
+    /// Check that the daemon is up and test every registered service, exiting non-zero if any
+    /// is unreachable
+    Doctor,
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let args = Cli::parse();
-    let client = Client::new(BASE_URL.to_string());
+    let mut client = Client::new(args.base_url.clone());
+    if let Some(token) = args.token.clone() {
+        client = client.with_admin_token(token);
+    }
 
     let command = args.command.clone();
     let result = match args.command {
-        Commands::Register { model_name, addr } => client.register(model_name, addr).await,
-        Commands::Unregister { target } => client.unregister(target).await,
-        Commands::List => client.list().await,
+        Commands::Register {
+            model_name,
+            addr,
+            pattern,
+            weight,
+            warmup_secs,
+            max_concurrency,
+            upstream_api_key,
+            capabilities,
+        } => {
+            // The first name goes in `model_name` so older servers still accept it
+            let mut names = model_name.into_iter();
+            let mut addrs = addr.into_iter();
+            client
+                .register(RegisterRequest {
+                    model_name: names.next().unwrap_or_default(),
+                    model_names: names.collect(),
+                    addr: addrs.next().unwrap_or_default(),
+                    addrs: addrs.collect(),
+                    pattern,
+                    weight,
+                    warmup_secs,
+                    max_concurrency,
+                    upstream_api_key,
+                    capabilities,
+                })
+                .await
+        }
+        Commands::RegisterBatch { ref file } => match read_registrations(file) {
+            Ok(requests) => client.register_all(&requests).await,
+            Err(e) => Err(e),
+        },
+        Commands::Unregister {
+            target,
+            addr,
+            model_name,
+            all,
+            yes: _,
+        } => match (target.or(addr), model_name) {
+            (Some(target), _) => client.unregister(target).await,
+            (None, Some(model_name)) => client.unregister_model(model_name).await,
+            // `--all` can only be given with `--yes`
+            (None, None) if all => client.unregister_all().await,
+            (None, None) => unreachable!("clap requires one selector"),
+        },
+        Commands::Drain { target } => client.drain(target).await,
+        Commands::List {
+            model_name,
+            offset,
+            limit,
+        } => {
+            client
+                .list(ListQuery {
+                    model_name,
+                    offset,
+                    limit,
+                })
+                .await
+        }
+        Commands::Status => client.status().await,
         Commands::Test { id } => client.test(id).await,
+        Commands::Doctor => client.doctor().await,
+        Commands::Bench {
+            ref model,
+            requests,
+            concurrency,
+        } => client.bench(model, requests, concurrency).await,
+        Commands::WaitReady {
+            ref model,
+            min,
+            timeout,
+        } => {
+            client
+                .wait_ready(model, min, Duration::from_secs(timeout))
+                .await
+        }
     };
 
     if let Err(e) = result {
-        handle_error(&*e, &command);
+        handle_error(&e, &command, &args.base_url);
+        // Deploy scripts rely on the exit status of register-batch,
+        // wait-ready and doctor
+        if matches!(
+            command,
+            Commands::RegisterBatch { .. } | Commands::WaitReady { .. } | Commands::Doctor
+        ) {
+            std::process::exit(1);
+        }
     }
 
     Ok(())
 }
 
-fn handle_error(e: &dyn std::error::Error, command: &Commands) {
-    let error_msg = e.to_string();
-
-    if error_msg.contains("Connection refused")
-        || error_msg.contains("connection")
-        || error_msg.contains("error sending request")
-    {
-        eprintln!(
-            "{} {}",
-            "✖".red().bold(),
-            "Cannot connect to llmproxyd server".red()
-        );
-        eprintln!(
-            "  {} Make sure the server is running on {}",
-            "→".bright_blue(),
-            BASE_URL.bright_cyan()
-        );
-        eprintln!(
-            "  {} Start it with: {}",
-            "→".bright_blue(),
-            "llmproxyd".bright_green()
-        );
-    } else if error_msg.contains("timeout") {
-        eprintln!("{} {}", "✖".red().bold(), "Request timed out".red());
-        eprintln!(
-            "  {} The server may be overloaded or unresponsive",
-            "→".bright_blue()
-        );
-    } else if error_msg.contains("json") || error_msg.contains("parse") {
-        eprintln!(
-            "{} {}",
-            "✖".red().bold(),
-            "Invalid response from server".red()
-        );
-        eprintln!(
-            "  {} Server may be incompatible or corrupted",
-            "→".bright_blue()
+/// Reads the registrations for `register-batch` from a JSON array in `path`.
+fn read_registrations(path: &Path) -> Result<Vec<RegisterRequest>, ClientError> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| ClientError::Other(format!("Failed to read {}: {}", path.display(), e)))?;
+    serde_json::from_str(&contents).map_err(|e| {
+        ClientError::Other(format!(
+            "Invalid registrations in {}: {}",
+            path.display(),
+            e
+        ))
+    })
+}
+
+/// Accepts `http(s)://host[:port]` URLs, normalized without a trailing slash.
+fn parse_base_url(value: &str) -> Result<String, String> {
+    let url = reqwest::Url::parse(value).map_err(|e| format!("invalid URL '{value}': {e}"))?;
+    if !matches!(url.scheme(), "http" | "https") || !url.has_host() {
+        return Err(format!(
+            "invalid URL '{value}': expected http://host:port or https://host:port"
+        ));
+    }
+    Ok(url.as_str().trim_end_matches('/').to_string())
+}
+
+fn handle_error(e: &ClientError, command: &Commands, base_url: &str) {
+    match e {
+        ClientError::ServerUnreachable(_) => {
+            eprintln!(
+                "{} {}",
+                "✖".red().bold(),
+                "Cannot connect to llmproxyd server".red()
+            );
+            eprintln!(
+                "  {} Make sure the server is running on {}",
+                "→".bright_blue(),
+                base_url.bright_cyan()
+            );
+            eprintln!(
+                "  {} Start it with: {}",
+                "→".bright_blue(),
+                "llmproxyd".bright_green()
+            );
+        }
+        ClientError::Timeout(_) => {
+            eprintln!("{} {}", "✖".red().bold(), "Request timed out".red());
+            eprintln!(
+                "  {} The server may be overloaded or unresponsive",
+                "→".bright_blue()
+            );
+        }
+        ClientError::InvalidResponse(_) => {
+            eprintln!(
+                "{} {}",
+                "✖".red().bold(),
+                "Invalid response from server".red()
+            );
+            eprintln!(
+                "  {} Server may be incompatible or corrupted",
+                "→".bright_blue()
+            );
+        }
+        ClientError::Server { .. } | ClientError::Other(_) => {
+            let operation = match command {
+                Commands::Register { .. } => "registration",
+                Commands::RegisterBatch { .. } => "batch registration",
+                Commands::Unregister { .. } => "unregistration",
+                Commands::Drain { .. } => "draining",
+                Commands::List { .. } => "listing services",
+                Commands::Status => "fetching status",
+                Commands::Test { .. } => "testing service",
+                Commands::Doctor => "checking services",
+                Commands::Bench { .. } => "benchmarking",
+                Commands::WaitReady { .. } => "waiting for model",
+            };
+
+            eprintln!(
+                "{} {} failed",
+                "✖".red().bold(),
+                format!(
+                    "{}{}",
+                    operation.chars().next().unwrap().to_uppercase(),
+                    &operation[1..]
+                )
+                .red()
+            );
+            eprintln!("  {} {}", "→".bright_blue(), e.to_string().bright_red());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_base_url_flag_and_env() {
+        // Kept in one test since the environment is shared between threads
+        std::env::remove_var("LLMPROXY_URL");
+        let cli = Cli::try_parse_from(["llmproxy", "list"]).unwrap();
+        assert_eq!(cli.base_url, DEFAULT_BASE_URL);
+
+        std::env::set_var("LLMPROXY_URL", "http://proxy.internal:8080/");
+        let cli = Cli::try_parse_from(["llmproxy", "list"]).unwrap();
+        assert_eq!(cli.base_url, "http://proxy.internal:8080");
+
+        let cli = Cli::try_parse_from(["llmproxy", "list", "--base-url", "https://10.0.0.5:11450"])
+            .unwrap();
+        assert_eq!(cli.base_url, "https://10.0.0.5:11450");
+        std::env::remove_var("LLMPROXY_URL");
+
+        assert!(
+            Cli::try_parse_from(["llmproxy", "--base-url", "localhost:11450", "list"]).is_err()
         );
-    } else {
-        let operation = match command {
-            Commands::Register { .. } => "registration",
-            Commands::Unregister { .. } => "unregistration",
-            Commands::List => "listing services",
-            Commands::Test { .. } => "testing service",
-        };
-
-        eprintln!(
-            "{} {} failed",
-            "✖".red().bold(),
-            format!(
-                "{}{}",
-                operation.chars().next().unwrap().to_uppercase(),
-                &operation[1..]
-            )
-            .red()
+        assert!(Cli::try_parse_from(["llmproxy", "--base-url", "not a url", "list"]).is_err());
+    }
+
+    #[test]
+    fn test_unregister_all_requires_yes() {
+        let error = Cli::try_parse_from(["llmproxy", "unregister", "--all"])
+            .err()
+            .unwrap();
+        assert_eq!(
+            error.kind(),
+            clap::error::ErrorKind::MissingRequiredArgument
         );
-        eprintln!("  {} {}", "→".bright_blue(), error_msg.bright_red());
+
+        let cli = Cli::try_parse_from(["llmproxy", "unregister", "--all", "--yes"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Unregister {
+                all: true,
+                target: None,
+                ..
+            }
+        ));
+
+        // Only one selector at a time
+        assert!(Cli::try_parse_from(["llmproxy", "unregister", "1", "--all", "--yes"]).is_err());
+    }
+
+    #[test]
+    fn test_read_registrations() {
+        let path = std::env::temp_dir().join(format!("llmproxy-batch-{}.json", std::process::id()));
+        std::fs::write(
+            &path,
+            r#"[{"model_name": "a", "addr": "localhost:8001"}, {"model_name": "b", "addr": "localhost:8002", "weight": 2}]"#,
+        )
+        .unwrap();
+        let requests = read_registrations(&path).unwrap();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[1].addr, "localhost:8002");
+        assert_eq!(requests[1].weight, Some(2));
+
+        std::fs::write(&path, r#"{"model_name": "a", "addr": "localhost:8001"}"#).unwrap();
+        assert!(matches!(
+            read_registrations(&path),
+            Err(ClientError::Other(message)) if message.starts_with("Invalid registrations")
+        ));
+        std::fs::remove_file(&path).unwrap();
+        assert!(read_registrations(&path).is_err());
     }
 }
@@ -1,6 +1,25 @@
-use clap::Parser;
+use axum::http::{HeaderName, HeaderValue};
+use clap::{Parser, ValueEnum};
 use clap_verbosity_flag::Verbosity;
-use std::net::{IpAddr, SocketAddr};
+use llmproxy::config::ConfigFile;
+use llmproxy::models::ModelAliases;
+use llmproxy::server::{
+    ModelConflict, ServerConfig, StatusRule, Strategy, TlsConfig, OPENAI_PATHS,
+};
+use regex::Regex;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+#[derive(Clone, Copy, ValueEnum)]
+enum LogFormat {
+    /// Human-readable lines
+    Text,
+    /// One JSON object per line, with span fields such as the model and backend
+    Json,
+}
 
 #[derive(Parser)]
 #[command(author, version, about)]
@@ -8,20 +27,480 @@ struct Cli {
     #[command(flatten)]
     verbosity: Verbosity,
 
-    #[arg(short, long, default_value = "11450")]
-    port: u16,
+    /// Log output format
+    #[arg(long, value_enum, default_value = "text")]
+    log_format: LogFormat,
+
+    /// JSON file with listener settings and backends to register at startup;
+    /// flags override its values
+    #[arg(long, value_name = "PATH")]
+    config: Option<PathBuf>,
+
+    /// Port to listen on [default: 11450]
+    #[arg(short, long)]
+    port: Option<u16>,
+
+    /// Address to listen on [default: 0.0.0.0]
+    #[arg(long)]
+    host: Option<IpAddr>,
+
+    /// HOST:PORT to listen on, instead of --host and --port (repeatable, e.g. to listen on
+    /// localhost and a LAN address)
+    #[arg(long = "bind", value_name = "HOST:PORT", conflicts_with_all = ["host", "port"])]
+    binds: Vec<SocketAddr>,
+
+    /// How many other replicas to try when a backend can't be reached
+    #[arg(long, default_value = "2")]
+    max_retries: usize,
+
+    /// Retries per second allowed across all requests (unlimited if unset)
+    #[arg(long, value_parser = parse_retry_budget)]
+    retry_budget: Option<f64>,
+
+    /// Seconds to wait for a backend response before returning 504, and then for each part of
+    /// its body before cutting it off [default: 300]
+    #[arg(long)]
+    upstream_timeout: Option<u64>,
+
+    /// Seconds a streaming response may wait on a client that stopped reading [default: 60]
+    #[arg(long)]
+    client_idle_timeout: Option<u64>,
+
+    /// Seconds between active health checks of registered backends (disabled if unset)
+    #[arg(long)]
+    health_check_interval: Option<u64>,
+
+    /// Consecutive failed health checks before a backend is unregistered
+    #[arg(long, default_value = "3", value_parser = clap::value_parser!(u32).range(1..))]
+    health_check_failures: u32,
+
+    /// Retries of a failed health probe within one check round before it counts as a failure
+    #[arg(long, default_value = "0")]
+    health_check_retries: u32,
+
+    /// Bearer token required by the admin endpoints
+    #[arg(long, env = "LLMPROXY_ADMIN_TOKEN", hide_env_values = true)]
+    admin_token: Option<String>,
+
+    /// Regex reducing model names to a metrics label (first capture group, or the whole match)
+    #[arg(long, value_parser = Regex::new)]
+    metric_model_regex: Option<Regex>,
+
+    /// Most model names one backend address may be registered for (unlimited if unset)
+    #[arg(long)]
+    max_models_per_backend: Option<usize>,
+
+    /// Largest request body buffered before forwarding; bigger bodies get a 413
+    #[arg(long, default_value = "16777216")]
+    max_body_bytes: usize,
+
+    /// Seconds to let in-flight requests finish after SIGTERM/SIGINT
+    #[arg(long, default_value = "120")]
+    shutdown_timeout: u64,
+
+    /// Route each client IP consistently to the same replica of a model
+    #[arg(long)]
+    sticky_by_ip: bool,
+
+    /// Header (e.g. X-Session-Id) whose value pins a conversation to one replica of its model
+    #[arg(long, value_name = "NAME", value_parser = HeaderName::from_str)]
+    session_header: Option<HeaderName>,
+
+    /// Backend response header (e.g. X-Cache-Node) marking the replica that holds a session's
+    /// cache; sessions then stick to the replica that last sent it instead of a hashed one
+    #[arg(long, value_name = "NAME", value_parser = HeaderName::from_str, requires = "session_header")]
+    affinity_header: Option<HeaderName>,
+
+    /// How to pick among a model's replicas: random (weighted), least-connections or round-robin
+    /// [default: random]
+    #[arg(long)]
+    strategy: Option<Strategy>,
+
+    /// Strategy for one model, overriding --strategy (repeatable)
+    #[arg(long = "model-strategy", value_name = "MODEL=STRATEGY", value_parser = parse_model_strategy)]
+    model_strategies: Vec<(String, Strategy)>,
+
+    /// Model to route to when the body and the X-Model header or model query parameter disagree:
+    /// body, header or reject (with a 400)
+    #[arg(long, default_value = "body")]
+    model_conflict: ModelConflict,
+
+    /// Add an x-llmproxy-route field naming the backend and strategy to forwarded JSON bodies
+    #[arg(long)]
+    inject_route_field: bool,
+
+    /// Action for upstream statuses as CODES=ACTION, e.g. 5xx=retry or 501=passthrough
+    /// (actions: passthrough, retry, fail; first match wins; repeatable)
+    #[arg(long = "status-policy", value_name = "CODES=ACTION")]
+    status_policy: Vec<StatusRule>,
+
+    /// Retry requests answered with 429 on another replica and back off the backend for its
+    /// Retry-After (5 seconds if unset, at most 60)
+    #[arg(long)]
+    retry_on_429: bool,
+
+    /// Seconds an unused upstream connection is kept open for reuse
+    #[arg(long, default_value = "30")]
+    pool_idle_timeout: u64,
+
+    /// Most idle connections kept per backend (unlimited if unset)
+    #[arg(long)]
+    pool_max_idle: Option<usize>,
+
+    /// Most connections open to each backend at once (unlimited if unset)
+    #[arg(long, value_name = "N")]
+    pool_max_connections: Option<NonZeroUsize>,
+
+    /// Seconds to wait for a free or new connection to a backend before answering 503 or
+    /// retrying another replica (no limit if unset)
+    #[arg(long, value_name = "SECS", value_parser = clap::value_parser!(u64).range(1..))]
+    acquire_timeout: Option<u64>,
+
+    /// Use HTTP/2 for every backend instead of negotiating the version
+    #[arg(long)]
+    http2: bool,
+
+    /// Failures within a minute that take a backend out of rotation (0 disables)
+    #[arg(long, default_value = "5")]
+    circuit_breaker_threshold: u32,
+
+    /// Seconds a tripped backend is skipped before a trial request
+    #[arg(long, default_value = "30")]
+    circuit_breaker_cooldown: u64,
+
+    /// Serve requests for ALIAS with the backends of MODEL; a model registered
+    /// under the alias name itself takes precedence (repeatable)
+    #[arg(long = "alias", value_name = "ALIAS=MODEL", value_parser = parse_alias)]
+    aliases: Vec<(String, String)>,
+
+    /// JSON file mapping aliases to models, e.g. {"gpt-4": "Qwen/Qwen2-7B-Instruct"};
+    /// --alias flags override its entries
+    #[arg(long, value_name = "PATH")]
+    alias_file: Option<PathBuf>,
+
+    /// Seconds after which an in-flight request is listed as long running in /stats
+    #[arg(long, default_value = "120")]
+    long_running_threshold: u64,
+
+    /// Send every request to the only registered model, whatever model it names
+    #[arg(long)]
+    single_model_passthrough: bool,
+
+    /// Reject registrations of backends that don't answer GET /health
+    #[arg(long)]
+    probe_on_register: bool,
+
+    /// Seconds a request waits for a replica under its --max-concurrency before a 503 (no waiting if unset)
+    #[arg(long)]
+    queue_timeout: Option<u64>,
 
-    #[arg(long, default_value = "0.0.0.0")]
-    host: IpAddr,
+    /// Queue timeout for one model, overriding --queue-timeout (repeatable)
+    #[arg(long = "model-queue-timeout", value_name = "MODEL=SECS", value_parser = parse_model_queue_timeout)]
+    model_queue_timeouts: Vec<(String, Duration)>,
+
+    /// Most requests waiting for a replica at once
+    #[arg(long, default_value = "100")]
+    max_queued_requests: usize,
+
+    /// Disable Nagle's algorithm on client connections to cut small-response latency
+    #[arg(long)]
+    tcp_nodelay: bool,
+
+    /// Length of the listener's queue of connections waiting to be accepted
+    #[arg(long, default_value = "1024")]
+    listen_backlog: u32,
+
+    /// Set SO_REUSEPORT on the listener so several daemons can share the port (Unix only)
+    #[arg(long)]
+    reuse_port: bool,
+
+    /// Cache up to N responses to identical temperature-0, non-streaming requests (0 disables)
+    #[arg(long, value_name = "N", default_value = "0")]
+    cache_size: usize,
+
+    /// Only answer from the cache when the backend fails a cacheable request
+    #[arg(long)]
+    cache_fallback: bool,
+
+    /// Let browser pages from ORIGIN call the proxy endpoints (repeatable, or * for any)
+    #[arg(long = "allow-origin", value_name = "ORIGIN")]
+    allow_origins: Vec<HeaderValue>,
+
+    /// End event streams whose backend fails midway with `data: <DATA>` and `data: [DONE]`
+    /// instead of cutting them off
+    #[arg(
+        long,
+        value_name = "DATA",
+        num_args = 0..=1,
+        default_missing_value = r#"{"error":"upstream disconnected"}"#
+    )]
+    stream_error_event: Option<String>,
+
+    /// Proxied requests each client (bearer token, or IP address) may send per window
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    rate_limit: Option<u32>,
+
+    /// Seconds over which --rate-limit requests are allowed
+    #[arg(long, default_value = "60", value_parser = clap::value_parser!(u64).range(1..))]
+    rate_limit_window: u64,
+
+    /// Address of a reverse proxy in front of llmproxy whose X-Forwarded-For is trusted to
+    /// name the client for --rate-limit (repeatable)
+    #[arg(long = "trusted-proxy", value_name = "IP")]
+    trusted_proxies: Vec<IpAddr>,
+
+    /// Report proxy errors (unknown model, no backends, bad gateway, timeout) in the OpenAI
+    /// error format
+    #[arg(long)]
+    openai_errors: bool,
+
+    /// Reject proxied requests without header NAME (e.g. X-Tenant-Id) with a 400 (repeatable)
+    #[arg(long = "require-header", value_name = "NAME", value_parser = HeaderName::from_str)]
+    required_headers: Vec<HeaderName>,
+
+    /// Another HOST:PORT that reaches this proxy (e.g. through a load balancer), refused as a
+    /// backend like the listen address to prevent request loops (repeatable)
+    #[arg(long = "self-addr", value_name = "HOST:PORT")]
+    self_addrs: Vec<String>,
+
+    /// Report the number of backends of each model in `/v1/models` (a non-standard field)
+    #[arg(long)]
+    models_backend_count: bool,
+
+    /// Proxy only requests for PATH (e.g. /v1/chat/completions) and answer others with a 404
+    /// (repeatable)
+    #[arg(long = "allow-path", value_name = "PATH")]
+    allowed_paths: Vec<String>,
+
+    /// Proxy only the OpenAI completion, chat, embedding and model list paths, unless
+    /// --allow-path names others
+    #[arg(long)]
+    strict_paths: bool,
+
+    /// Log one line per proxied request with the client, model, backend, status, body sizes
+    /// and duration, once its response has been sent
+    #[arg(long)]
+    access_log: bool,
+
+    /// Seconds clients are told to wait (in Retry-After) before retrying when no backend is
+    /// registered or available
+    #[arg(long, value_name = "SECS", default_value = "5")]
+    unavailable_retry_after: u64,
+
+    /// Log the "No vLLM servers registered" warning at most once per SECS while no backend is
+    /// registered; 0 logs it for every request
+    #[arg(long, value_name = "SECS", default_value = "10")]
+    empty_registry_log_interval: u64,
+
+    /// PEM certificate chain to serve HTTPS with, leaf first; requires --tls-key
+    #[arg(long, value_name = "PATH", requires = "tls_key")]
+    tls_cert: Option<PathBuf>,
+
+    /// PEM private key for --tls-cert
+    #[arg(long, value_name = "PATH", requires = "tls_cert")]
+    tls_key: Option<PathBuf>,
+}
+
+fn parse_retry_budget(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(budget) if budget.is_finite() && budget >= 0.0 => Ok(budget),
+        Ok(_) => Err("must be a finite number >= 0".to_string()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+fn parse_model_strategy(s: &str) -> Result<(String, Strategy), String> {
+    match s.rsplit_once('=') {
+        Some((model, strategy)) if !model.trim().is_empty() => {
+            Ok((model.trim().to_string(), strategy.trim().parse()?))
+        }
+        _ => Err(format!("expected MODEL=STRATEGY, got '{}'", s)),
+    }
+}
+
+fn parse_model_queue_timeout(s: &str) -> Result<(String, Duration), String> {
+    match s.rsplit_once('=') {
+        Some((model, secs)) if !model.trim().is_empty() => {
+            let secs: u64 = secs
+                .trim()
+                .parse()
+                .map_err(|e| format!("invalid SECS: {e}"))?;
+            Ok((model.trim().to_string(), Duration::from_secs(secs)))
+        }
+        _ => Err(format!("expected MODEL=SECS, got '{}'", s)),
+    }
+}
+
+fn parse_alias(s: &str) -> Result<(String, String), String> {
+    match s.split_once('=') {
+        Some((alias, model)) if !alias.trim().is_empty() && !model.trim().is_empty() => {
+            Ok((alias.trim().to_string(), model.trim().to_string()))
+        }
+        _ => Err(format!("expected ALIAS=MODEL, got '{}'", s)),
+    }
+}
+
+fn load_alias_file(path: &Path) -> Result<ModelAliases, String> {
+    let contents = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    serde_json::from_str(&contents).map_err(|e| e.to_string())
 }
 
 #[tokio::main]
 async fn main() {
     let cli = Cli::parse();
-    tracing_subscriber::fmt()
-        .with_max_level(cli.verbosity)
-        .init();
+    let subscriber = tracing_subscriber::fmt().with_max_level(cli.verbosity);
+    match cli.log_format {
+        LogFormat::Text => subscriber.init(),
+        LogFormat::Json => subscriber.json().init(),
+    }
+
+    let mut aliases = match cli.alias_file.as_deref().map(load_alias_file) {
+        Some(Ok(aliases)) => aliases,
+        Some(Err(e)) => {
+            eprintln!(
+                "Failed to load alias file {}: {}",
+                cli.alias_file.unwrap().display(),
+                e
+            );
+            std::process::exit(1);
+        }
+        None => ModelAliases::default(),
+    };
+    aliases.extend(cli.aliases);
+
+    let file = match cli.config.as_deref().map(ConfigFile::load) {
+        Some(Ok(file)) => file,
+        Some(Err(e)) => {
+            eprintln!(
+                "Failed to load config file {}: {}",
+                cli.config.unwrap().display(),
+                e
+            );
+            std::process::exit(1);
+        }
+        None => ConfigFile::default(),
+    };
+
+    let tls = match (cli.tls_cert.as_deref(), cli.tls_key.as_deref()) {
+        (Some(cert), Some(key)) => match TlsConfig::load(cert, key) {
+            Ok(tls) => Some(tls),
+            Err(e) => {
+                eprintln!("Failed to load TLS certificate: {}", e);
+                std::process::exit(1);
+            }
+        },
+        _ => None,
+    };
+
+    let addrs = if cli.binds.is_empty() {
+        vec![SocketAddr::new(
+            cli.host
+                .or(file.host)
+                .unwrap_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED)),
+            cli.port.or(file.port).unwrap_or(11450),
+        )]
+    } else {
+        cli.binds
+    };
+    let allowed_paths = if cli.strict_paths && cli.allowed_paths.is_empty() {
+        OPENAI_PATHS.iter().map(|path| path.to_string()).collect()
+    } else {
+        cli.allowed_paths
+    };
+    let config = ServerConfig {
+        max_retries: cli.max_retries,
+        retry_budget: cli.retry_budget,
+        upstream_timeout: Duration::from_secs(
+            cli.upstream_timeout
+                .or(file.upstream_timeout)
+                .unwrap_or(300),
+        ),
+        client_idle_timeout: Duration::from_secs(
+            cli.client_idle_timeout
+                .or(file.client_idle_timeout)
+                .unwrap_or(60),
+        ),
+        health_check_interval: cli.health_check_interval.map(Duration::from_secs),
+        health_check_failures: cli.health_check_failures,
+        health_check_retries: cli.health_check_retries,
+        admin_token: cli.admin_token,
+        metric_model_regex: cli.metric_model_regex,
+        max_models_per_backend: cli.max_models_per_backend,
+        max_body_bytes: cli.max_body_bytes,
+        shutdown_timeout: Duration::from_secs(cli.shutdown_timeout),
+        sticky_by_ip: cli.sticky_by_ip,
+        session_header: cli.session_header,
+        affinity_header: cli.affinity_header,
+        strategy: cli.strategy.or(file.strategy).unwrap_or_default(),
+        model_strategies: cli.model_strategies.into_iter().collect(),
+        model_conflict: cli.model_conflict,
+        status_policy: cli.status_policy,
+        retry_on_429: cli.retry_on_429,
+        inject_route_field: cli.inject_route_field,
+        pool_idle_timeout: Duration::from_secs(cli.pool_idle_timeout),
+        pool_max_idle_per_host: cli.pool_max_idle,
+        pool_max_connections: cli.pool_max_connections.map(NonZeroUsize::get),
+        acquire_timeout: cli.acquire_timeout.map(Duration::from_secs),
+        http2_only: cli.http2,
+        circuit_breaker_threshold: cli.circuit_breaker_threshold,
+        circuit_breaker_cooldown: Duration::from_secs(cli.circuit_breaker_cooldown),
+        aliases,
+        long_running_threshold: Duration::from_secs(cli.long_running_threshold),
+        single_model_passthrough: cli.single_model_passthrough,
+        probe_on_register: cli.probe_on_register,
+        queue_timeout: cli.queue_timeout.map(Duration::from_secs),
+        model_queue_timeouts: cli.model_queue_timeouts.into_iter().collect(),
+        max_queued_requests: cli.max_queued_requests,
+        tcp_nodelay: cli.tcp_nodelay,
+        listen_backlog: cli.listen_backlog,
+        reuse_port: cli.reuse_port,
+        cache_size: cli.cache_size,
+        cache_fallback: cli.cache_fallback,
+        allow_origins: cli.allow_origins,
+        stream_error_event: cli.stream_error_event,
+        rate_limit: cli.rate_limit,
+        rate_limit_window: Duration::from_secs(cli.rate_limit_window),
+        trusted_proxies: cli.trusted_proxies,
+        initial_servers: file.servers,
+        openai_errors: cli.openai_errors,
+        required_headers: cli.required_headers,
+        self_addrs: cli.self_addrs,
+        path_prefixes: file.path_prefixes.into_iter().collect(),
+        models_backend_count: cli.models_backend_count,
+        allowed_paths,
+        access_log: cli.access_log,
+        unavailable_retry_after: Duration::from_secs(cli.unavailable_retry_after),
+        empty_registry_log_interval: Duration::from_secs(cli.empty_registry_log_interval),
+        tls,
+    };
+    llmproxy::server::run(addrs, config).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_budget_must_be_finite_and_non_negative() {
+        let parse = |value: &str| {
+            Cli::try_parse_from(["llmproxyd".to_string(), format!("--retry-budget={value}")])
+        };
+        assert_eq!(parse("2.5").unwrap().retry_budget, Some(2.5));
+        assert_eq!(parse("0").unwrap().retry_budget, Some(0.0));
+        for invalid in ["-1", "NaN", "inf", "many"] {
+            assert!(parse(invalid).is_err(), "{invalid}");
+        }
+    }
 
-    let addr = SocketAddr::new(cli.host, cli.port);
-    llmproxy::server::run(addr).await;
+    #[test]
+    fn test_health_check_failures_must_be_positive() {
+        let parse = |value: &str| {
+            Cli::try_parse_from([
+                "llmproxyd".to_string(),
+                format!("--health-check-failures={value}"),
+            ])
+        };
+        assert_eq!(parse("1").unwrap().health_check_failures, 1);
+        assert!(parse("0").is_err());
+    }
 }
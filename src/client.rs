@@ -1,13 +1,148 @@
 use crate::models::{
-    ProxyServerInfo, RegisterRequest, ResponseStatus, ServerResponse, TestRequest,
+    BatchRegisterResult, DrainRequest, ListQuery, ProxyServerInfo, RegisterRequest, ResponseStatus,
+    ServerResponse, Stats, TestRequest, TestResult, UnregisterRequest, TOTAL_COUNT_HEADER,
 };
 use colored::*;
 use reqwest::Client as ReqwestClient;
 use reqwest::StatusCode;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
+/// How often `wait_ready` polls the daemon.
+const WAIT_READY_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Error of the [`Client`] methods.
+#[derive(Debug)]
+pub enum ClientError {
+    /// llmproxyd couldn't be connected to, or the connection broke.
+    ServerUnreachable(reqwest::Error),
+    /// llmproxyd didn't answer in time.
+    Timeout(reqwest::Error),
+    /// llmproxyd's answer couldn't be read or wasn't the expected JSON.
+    InvalidResponse(String),
+    /// llmproxyd answered with an error status.
+    Server { status: StatusCode, message: String },
+    /// The operation failed for another reason, e.g. a service index that is
+    /// out of range.
+    Other(String),
+}
+
+impl ClientError {
+    /// Builds a [`ClientError::Server`], taking the message from a
+    /// [`ServerResponse`] body if there is one.
+    async fn from_response(status: StatusCode, response: reqwest::Response) -> Self {
+        let body = response.text().await.unwrap_or_default();
+        let message = serde_json::from_str::<ServerResponse>(&body)
+            .map(|response| response.message)
+            .unwrap_or(body);
+        Self::Server { status, message }
+    }
+}
+
+impl std::fmt::Display for ClientError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ServerUnreachable(e) | Self::Timeout(e) => write!(f, "{}", e),
+            Self::InvalidResponse(message) | Self::Other(message) => f.write_str(message),
+            Self::Server { status, message } => write!(f, "{} ({})", message, status),
+        }
+    }
+}
+
+impl std::error::Error for ClientError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::ServerUnreachable(e) | Self::Timeout(e) => Some(e),
+            Self::InvalidResponse(_) | Self::Server { .. } | Self::Other(_) => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for ClientError {
+    fn from(e: reqwest::Error) -> Self {
+        // Timeouts while connecting are timeouts too
+        if e.is_timeout() {
+            Self::Timeout(e)
+        } else if e.is_connect() || e.is_request() {
+            Self::ServerUnreachable(e)
+        } else if e.is_decode() || e.is_body() {
+            Self::InvalidResponse(e.to_string())
+        } else if let Some(status) = e.status() {
+            Self::Server {
+                status,
+                message: e.to_string(),
+            }
+        } else {
+            Self::ServerUnreachable(e)
+        }
+    }
+}
+
+/// A page of `GET /list`, from [`Client::list_servers_page`].
+#[derive(Debug, Clone)]
+pub struct ServerPage {
+    pub servers: Vec<ProxyServerInfo>,
+    /// Servers matching the model filter across all pages.
+    pub total: usize,
+}
+
+/// Reachability of one registered backend, from [`Client::run_doctor`].
+#[derive(Debug, Clone)]
+pub struct BackendCheck {
+    pub server: ProxyServerInfo,
+    pub result: TestResult,
+}
+
+impl BackendCheck {
+    pub fn reachable(&self) -> bool {
+        self.result.status == ResponseStatus::Success
+    }
+}
+
+/// Outcome of [`Client::run_bench`].
+#[derive(Debug, Clone)]
+pub struct BenchReport {
+    /// Latencies of the successful requests, sorted.
+    pub latencies: Vec<Duration>,
+    /// Requests that failed to send or got an error status.
+    pub errors: usize,
+    /// Wall-clock time of the whole run.
+    pub elapsed: Duration,
+}
+
+impl BenchReport {
+    pub fn requests(&self) -> usize {
+        self.latencies.len() + self.errors
+    }
+
+    pub fn error_rate(&self) -> f64 {
+        match self.requests() {
+            0 => 0.0,
+            requests => self.errors as f64 / requests as f64,
+        }
+    }
+
+    /// Nearest-rank percentile `p` (0-100) of the successful requests'
+    /// latencies, or `None` if none succeeded.
+    pub fn percentile(&self, p: f64) -> Option<Duration> {
+        percentile(&self.latencies, p)
+    }
+}
+
+fn percentile(sorted: &[Duration], p: f64) -> Option<Duration> {
+    if sorted.is_empty() {
+        return None;
+    }
+    let rank = (p / 100.0 * sorted.len() as f64).ceil() as usize;
+    Some(sorted[rank.clamp(1, sorted.len()) - 1])
+}
+
+#[derive(Clone)]
 pub struct Client {
     http_client: ReqwestClient,
     base_url: String,
+    admin_token: Option<String>,
 }
 
 impl Client {
@@ -15,6 +150,22 @@ impl Client {
         Self {
             http_client: ReqwestClient::new(),
             base_url,
+            admin_token: None,
+        }
+    }
+
+    /// Sends `token` as a bearer token on admin requests.
+    pub fn with_admin_token(mut self, token: String) -> Self {
+        self.admin_token = Some(token);
+        self
+    }
+
+    /// Starts a POST to an admin endpoint, attaching the admin token if set.
+    fn admin_post(&self, url: &str) -> reqwest::RequestBuilder {
+        let request = self.http_client.post(url);
+        match &self.admin_token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
         }
     }
 
@@ -28,31 +179,85 @@ impl Client {
         Ok(())
     }
 
-    pub async fn register(
-        &self,
-        model_name: String,
-        addr: String,
-    ) -> Result<(), Box<dyn std::error::Error>> {
+    pub async fn register(&self, request: RegisterRequest) -> Result<(), ClientError> {
         self.check_server_status().await?;
         let url = format!("{}/register", self.base_url);
-        let response = self
-            .http_client
-            .post(&url)
-            .json(&RegisterRequest {
-                model_name: model_name.clone(),
-                addr: addr.clone(),
-            })
-            .send()
-            .await?;
+        let response = self.admin_post(&url).json(&request).send().await?;
 
         handle_response(
             response,
-            Some(&format!("Registered {} at {}", model_name, addr)),
+            Some(&format!(
+                "Registered {} at {}",
+                request.names().join(", "),
+                request.addr
+            )),
         )
         .await
     }
 
-    pub async fn unregister(&self, target: String) -> Result<(), Box<dyn std::error::Error>> {
+    /// Registers without any console output, returning whether the daemon
+    /// added a new entry (`false` if it already had this registration).
+    async fn register_quiet(&self, request: &RegisterRequest) -> Result<bool, ClientError> {
+        let url = format!("{}/register", self.base_url);
+        let response = self.admin_post(&url).json(request).send().await?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok(status == StatusCode::CREATED);
+        }
+
+        Err(ClientError::from_response(status, response).await)
+    }
+
+    /// Registers several backends in one request, returning the outcome of
+    /// each in the order given. Failed entries don't make this an `Err`.
+    pub async fn register_batch(
+        &self,
+        requests: &[RegisterRequest],
+    ) -> Result<Vec<BatchRegisterResult>, ClientError> {
+        let url = format!("{}/register/batch", self.base_url);
+        let response = self.admin_post(&url).json(requests).send().await?;
+
+        let status = response.status();
+        if !status.is_success() {
+            return Err(ClientError::from_response(status, response).await);
+        }
+        Ok(response.json().await?)
+    }
+
+    /// Registers several backends in one request and prints the outcome of
+    /// each, failing if any of them was rejected.
+    pub async fn register_all(&self, requests: &[RegisterRequest]) -> Result<(), ClientError> {
+        self.check_server_status().await?;
+        let results = self.register_batch(requests).await?;
+        for (request, result) in requests.iter().zip(&results) {
+            let context = format!("{} at {}", request.names().join(", "), result.addr);
+            match result.status {
+                ResponseStatus::Error => {
+                    println!("✖ {}", context.red().bold());
+                    println!("  {} {}", "→".bright_blue(), result.message.bright_red());
+                }
+                _ => {
+                    println!("✔ {}", context.green().bold());
+                    println!("  {} {}", "→".bright_blue(), result.message.bright_black());
+                }
+            }
+        }
+
+        let failed = results
+            .iter()
+            .filter(|result| result.status == ResponseStatus::Error)
+            .count();
+        if failed > 0 {
+            return Err(ClientError::Other(format!(
+                "{} of {} registrations were rejected",
+                failed,
+                results.len()
+            )));
+        }
+        Ok(())
+    }
+
+    pub async fn unregister(&self, target: String) -> Result<(), ClientError> {
         self.check_server_status().await?;
 
         // Check if the input is a number (index) or an address
@@ -64,11 +269,10 @@ impl Client {
 
         let url = format!("{}/unregister", self.base_url);
         let response = self
-            .http_client
-            .post(&url)
-            .json(&RegisterRequest {
-                model_name: "".to_string(), // The server doesn't use this for unregistering
+            .admin_post(&url)
+            .json(&UnregisterRequest {
                 addr: actual_addr.clone(),
+                ..Default::default()
             })
             .send()
             .await?;
@@ -82,34 +286,157 @@ impl Client {
         handle_response(response, Some(&context)).await
     }
 
-    async fn resolve_index_to_address(
+    /// Stops routing new requests to a service, by index or address, while
+    /// its requests in flight finish.
+    pub async fn drain(&self, target: String) -> Result<(), ClientError> {
+        self.check_server_status().await?;
+
+        let actual_addr = if target.parse::<usize>().is_ok() {
+            self.resolve_index_to_address(&target).await?
+        } else {
+            target.clone()
+        };
+
+        let url = format!("{}/drain", self.base_url);
+        let response = self
+            .admin_post(&url)
+            .json(&DrainRequest {
+                addr: actual_addr.clone(),
+            })
+            .send()
+            .await?;
+
+        let context = format!(
+            "Draining service at {}; unregister it once its requests finish",
+            actual_addr
+        );
+        handle_response(response, Some(&context)).await
+    }
+
+    /// Unregisters every service of `model_name` in one call.
+    pub async fn unregister_model(&self, model_name: String) -> Result<(), ClientError> {
+        self.check_server_status().await?;
+
+        let url = format!("{}/unregister", self.base_url);
+        let response = self
+            .admin_post(&url)
+            .json(&UnregisterRequest {
+                model_name,
+                ..Default::default()
+            })
+            .send()
+            .await?;
+
+        handle_response(response, None).await
+    }
+
+    /// Unregisters every service.
+    pub async fn unregister_all(&self) -> Result<(), ClientError> {
+        self.check_server_status().await?;
+
+        let url = format!("{}/unregister", self.base_url);
+        let response = self
+            .admin_post(&url)
+            .json(&UnregisterRequest {
+                all: true,
+                ..Default::default()
+            })
+            .send()
+            .await?;
+
+        handle_response(response, None).await
+    }
+
+    /// Polls `/list` until at least `min` healthy servers can serve `model`,
+    /// failing once `timeout` has elapsed. Connection errors count as not
+    /// ready yet, since the daemon itself may still be starting.
+    pub async fn wait_ready(
         &self,
-        index_str: &str,
-    ) -> Result<String, Box<dyn std::error::Error>> {
-        let index: usize = index_str
-            .parse()
-            .map_err(|_| format!("Invalid index '{}'", index_str))?;
+        model: &str,
+        min: usize,
+        timeout: Duration,
+    ) -> Result<(), ClientError> {
+        let deadline = tokio::time::Instant::now() + timeout;
+        let mut last_ready = None;
+        loop {
+            let ready = match self.count_ready(model).await {
+                Ok(ready) => ready,
+                Err(e) => {
+                    tracing::debug!("Failed to query llmproxyd: {}", e);
+                    0
+                }
+            };
+            if ready >= min {
+                println!(
+                    "✔ {}",
+                    format!("{} has {} healthy service(s)", model, ready)
+                        .green()
+                        .bold()
+                );
+                return Ok(());
+            }
+            if last_ready != Some(ready) {
+                println!(
+                    "  {} {} has {}/{} healthy service(s), waiting...",
+                    "→".bright_blue(),
+                    model,
+                    ready,
+                    min
+                );
+                last_ready = Some(ready);
+            }
 
-        if index == 0 {
-            return Err("Service indices start from 1, not 0".to_string().into());
+            if tokio::time::Instant::now() + WAIT_READY_POLL_INTERVAL > deadline {
+                return Err(ClientError::Other(format!(
+                    "Timed out after {}s waiting for {} healthy service(s) of {}",
+                    timeout.as_secs(),
+                    min,
+                    model
+                )));
+            }
+            tokio::time::sleep(WAIT_READY_POLL_INTERVAL).await;
         }
+    }
 
-        // Get the current list of services
-        let url = format!("{}/list", self.base_url);
-        let response = self.http_client.get(&url).send().await?;
+    /// Number of healthy servers registered for `model`, by name or pattern.
+    async fn count_ready(&self, model: &str) -> Result<usize, ClientError> {
+        let server_list = self.list_servers(None).await?;
 
-        if !response.status().is_success() {
-            return Err("Failed to retrieve service list to resolve index".into());
+        Ok(server_list
+            .iter()
+            .filter(|server| server.healthy)
+            .filter(|server| {
+                server.model_names.iter().any(|name| {
+                    if server.pattern {
+                        globset::Glob::new(name)
+                            .is_ok_and(|glob| glob.compile_matcher().is_match(model))
+                    } else {
+                        name == model
+                    }
+                })
+            })
+            .count())
+    }
+
+    async fn resolve_index_to_address(&self, index_str: &str) -> Result<String, ClientError> {
+        let index: usize = index_str
+            .parse()
+            .map_err(|_| ClientError::Other(format!("Invalid index '{}'", index_str)))?;
+
+        if index == 0 {
+            return Err(ClientError::Other(
+                "Service indices start from 1, not 0".to_string(),
+            ));
         }
 
-        let server_list: Vec<ProxyServerInfo> = response.json().await?;
+        let server_list = self.list_servers(None).await?;
 
         if server_list.is_empty() {
-            return Err("No services are registered".into());
+            return Err(ClientError::Other("No services are registered".to_string()));
         }
 
         if index > server_list.len() {
-            return Err(format!(
+            return Err(ClientError::Other(format!(
                 "Index {} not found. Only {} service{} registered.",
                 index,
                 server_list.len(),
@@ -118,90 +445,286 @@ impl Client {
                 } else {
                     "s are"
                 }
-            )
-            .into());
+            )));
         }
 
         Ok(server_list[index - 1].addr.clone())
     }
 
-    pub async fn list(&self) -> Result<(), Box<dyn std::error::Error>> {
-        self.check_server_status().await?;
+    /// Registered servers, optionally only those of `model_name`. An empty
+    /// registry is `Ok(vec![])`.
+    pub async fn list_servers(
+        &self,
+        model_name: Option<&str>,
+    ) -> Result<Vec<ProxyServerInfo>, ClientError> {
+        Ok(self
+            .list_servers_page(ListQuery {
+                model_name: model_name.map(str::to_string),
+                ..Default::default()
+            })
+            .await?
+            .servers)
+    }
+
+    /// The page of registered servers selected by `query`, with the number
+    /// of servers matching its model filter on all pages.
+    pub async fn list_servers_page(&self, query: ListQuery) -> Result<ServerPage, ClientError> {
         let url = format!("{}/list", self.base_url);
-        let response = self.http_client.get(&url).send().await?;
+        let response = self.http_client.get(&url).query(&query).send().await?;
 
         let status = response.status();
-        if status.is_success() {
-            let server_list: Vec<ProxyServerInfo> = response.json().await?;
-            if server_list.is_empty() {
-                println!(
-                    "{} {}",
-                    "ℹ".bright_blue().bold(),
-                    "No model services are currently registered".bright_black()
-                );
-                println!(
-                    "  {} Use {} to register a new service",
-                    "→".bright_blue(),
-                    "llmproxy register --model-name <MODEL> --addr <ADDRESS>".bright_green()
-                );
-            } else {
-                // Calculate column widths
-                let label_width = 5; // "Label"
-                let mut model_width = 5; // "Model"
-                let mut addr_width = 7; // "Address"
-
-                for server in &server_list {
-                    model_width = model_width.max(server.model_name.len());
-                    addr_width = addr_width.max(server.addr.len());
+        if !status.is_success() {
+            return Err(ClientError::from_response(status, response).await);
+        }
+        let total = response
+            .headers()
+            .get(TOTAL_COUNT_HEADER)
+            .and_then(|value| value.to_str().ok()?.parse().ok());
+        let mut servers: Vec<ProxyServerInfo> = response.json().await?;
+        // Daemons from before several names per server only send `model_name`
+        for server in &mut servers {
+            if server.model_names.is_empty() && !server.model_name.is_empty() {
+                server.model_names.push(server.model_name.clone());
+            }
+        }
+        Ok(ServerPage {
+            // Older daemons don't page and always send everything
+            total: total.unwrap_or(servers.len()),
+            servers,
+        })
+    }
+
+    pub async fn list(&self, query: ListQuery) -> Result<(), ClientError> {
+        self.check_server_status().await?;
+        let model_name = query.model_name.clone();
+        let offset = query.offset.unwrap_or(0);
+        let ServerPage {
+            servers: server_list,
+            total,
+        } = match self.list_servers_page(query).await {
+            Ok(page) => page,
+            Err(ClientError::Server { status, message }) => {
+                print_error_status(status, &message);
+                return Ok(());
+            }
+            Err(e) => return Err(e),
+        };
+
+        if server_list.is_empty() && total > 0 {
+            println!(
+                "{} {}",
+                "ℹ".bright_blue().bold(),
+                format!("No services past the first {}", total).bright_black()
+            );
+        } else if server_list.is_empty() && model_name.is_some() {
+            println!(
+                "{} {}",
+                "ℹ".bright_blue().bold(),
+                format!(
+                    "No services match model '{}'",
+                    model_name.unwrap_or_default()
+                )
+                .bright_black()
+            );
+            println!(
+                "  {} Run {} to see all services",
+                "→".bright_blue(),
+                "llmproxy list".bright_green()
+            );
+        } else if server_list.is_empty() {
+            println!(
+                "{} {}",
+                "ℹ".bright_blue().bold(),
+                "No model services are currently registered".bright_black()
+            );
+            println!(
+                "  {} Use {} to register a new service",
+                "→".bright_blue(),
+                "llmproxy register --model-name <MODEL> --addr <ADDRESS>".bright_green()
+            );
+        } else {
+            // Calculate column widths
+            let label_width = 5; // "Label"
+            let mut model_width = 5; // "Model"
+            let mut addr_width = 7; // "Address"
+
+            let display_addr = |server: &ProxyServerInfo| {
+                if server.scheme == "https" {
+                    format!("https://{}", server.addr)
+                } else {
+                    server.addr.clone()
                 }
+            };
+
+            for server in &server_list {
+                model_width = model_width.max(server.model_names.join(", ").len());
+                addr_width = addr_width.max(display_addr(server).len());
+            }
+
+            // Print header
+            println!(
+                "{:<width_label$}  {:<width_model$}  {:<width_addr$}  {:>6}  {:>6}  {:>6}  {:>8}  \
+                 Circuit",
+                "Label",
+                "Model",
+                "Address",
+                "Weight",
+                "Share",
+                "Active",
+                "Served",
+                width_label = label_width,
+                width_model = model_width,
+                width_addr = addr_width
+            );
 
-                // Print header
+            // Print rows
+            for (position, server) in server_list.iter().enumerate() {
+                // Older daemons don't report the index
+                let index = if server.index > 0 {
+                    server.index
+                } else {
+                    position + 1
+                };
+                let label = format!("#{}", index);
                 println!(
-                    "{:<width_label$}  {:<width_model$}  {:<width_addr$}",
-                    "Label",
-                    "Model",
-                    "Address",
+                    "{:<width_label$}  {:<width_model$}  {:<width_addr$}  {:>6}  {:>5.1}%  {:>6}  \
+                     {:>8}  {}{}",
+                    label.bright_cyan(),
+                    server.model_names.join(", "),
+                    display_addr(server),
+                    server.weight,
+                    server.traffic_percent,
+                    server.in_flight,
+                    server.total_served,
+                    match server.circuit.as_str() {
+                        "open" => "open".red(),
+                        "half-open" => "half-open".yellow(),
+                        _ => "closed".green(),
+                    },
+                    if server.draining {
+                        "  draining".yellow()
+                    } else if server.warming_up {
+                        "  warming up".cyan()
+                    } else {
+                        "".normal()
+                    },
                     width_label = label_width,
                     width_model = model_width,
                     width_addr = addr_width
                 );
+            }
 
-                // Print rows
-                for (index, server) in server_list.iter().enumerate() {
-                    let label = format!("#{}", index + 1);
-                    println!(
-                        "{:<width_label$}  {:<width_model$}  {:<width_addr$}",
-                        label.bright_cyan(),
-                        server.model_name,
-                        server.addr,
-                        width_label = label_width,
-                        width_model = model_width,
-                        width_addr = addr_width
-                    );
-                }
-
+            if server_list.len() < total {
                 println!();
                 println!(
-                    "{} You can unregister services by index or address:",
-                    "💡".bright_yellow()
+                    "{} Showing {}-{} of {} services; page with {}",
+                    "ℹ".bright_blue().bold(),
+                    offset + 1,
+                    offset + server_list.len(),
+                    total,
+                    "--offset <N> --limit <N>".bright_green()
                 );
+            }
+
+            println!();
+            println!(
+                "{} You can unregister services by index or address:",
+                "💡".bright_yellow()
+            );
+            println!(
+                "  {} {}",
+                "→".bright_blue(),
+                "llmproxy unregister 1".bright_green()
+            );
+            println!(
+                "  {} {}",
+                "→".bright_blue(),
+                "llmproxy unregister localhost:8001".bright_green()
+            );
+        }
+        Ok(())
+    }
+
+    /// Prints replica and healthy counts per model, plus requests that have
+    /// been running for a long time.
+    pub async fn status(&self) -> Result<(), ClientError> {
+        self.check_server_status().await?;
+        let url = format!("{}/stats", self.base_url);
+        let response = self.http_client.get(&url).send().await?;
+
+        let status = response.status();
+        if !status.is_success() {
+            return handle_error_response(status, response).await;
+        }
+        let stats: Stats = response.json().await?;
+
+        if stats.models.is_empty() {
+            println!(
+                "{} {}",
+                "ℹ".bright_blue().bold(),
+                "No model services are currently registered".bright_black()
+            );
+        } else {
+            let model_width = stats
+                .models
+                .iter()
+                .map(|model| model.model_name.len())
+                .max()
+                .unwrap_or_default()
+                .max(5); // "Model"
+
+            println!(
+                "{:<width_model$}  {:>8}  {:>7}",
+                "Model",
+                "Replicas",
+                "Healthy",
+                width_model = model_width
+            );
+            for model in &stats.models {
+                let healthy = format!("{:>7}", model.healthy_count);
                 println!(
-                    "  {} {}",
-                    "→".bright_blue(),
-                    "llmproxy unregister 1".bright_green()
+                    "{:<width_model$}  {:>8}  {}",
+                    model.model_name,
+                    model.replica_count,
+                    if model.healthy_count == model.replica_count {
+                        healthy.green()
+                    } else if model.healthy_count > 0 {
+                        healthy.yellow()
+                    } else {
+                        healthy.red()
+                    },
+                    width_model = model_width
                 );
+            }
+        }
+
+        println!();
+        println!("{} request(s) in flight", stats.in_flight);
+        if !stats.long_running.is_empty() {
+            println!(
+                "{} {} request(s) running for a long time:",
+                "⚠".yellow().bold(),
+                stats.long_running.len()
+            );
+            for request in &stats.long_running {
                 println!(
-                    "  {} {}",
+                    "  {} {} on {} for {:.0}s{}",
                     "→".bright_blue(),
-                    "llmproxy unregister localhost:8001".bright_green()
+                    request.model_name,
+                    request.addr,
+                    request.elapsed_secs,
+                    request
+                        .request_id
+                        .as_deref()
+                        .map(|id| format!(" (request id {})", id))
+                        .unwrap_or_default()
                 );
             }
-        } else {
-            handle_error_response(status, response).await?;
         }
         Ok(())
     }
-    pub async fn test(&self, id: String) -> Result<(), Box<dyn std::error::Error>> {
+
+    pub async fn test(&self, id: String) -> Result<(), ClientError> {
         self.check_server_status().await?;
         let actual_addr = if id.parse::<usize>().is_ok() {
             self.resolve_index_to_address(&id).await?
@@ -211,22 +734,305 @@ impl Client {
 
         let url = format!("{}/test", self.base_url);
         let response = self
-            .http_client
-            .post(&url)
+            .admin_post(&url)
             .json(&TestRequest {
                 addr: actual_addr.clone(),
             })
             .send()
             .await?;
 
-        handle_response(response, None).await
+        let status = response.status();
+        let result: TestResult = response.json().await?;
+        match result.status {
+            ResponseStatus::Success => println!("✔ {}", result.message.green()),
+            _ => println!("✖ {} ({})", result.message.red().bold(), status),
+        }
+        if let (Some(latency_ms), Some(upstream_status)) =
+            (result.latency_ms, result.upstream_status)
+        {
+            println!(
+                "  {} {}",
+                "→".bright_blue(),
+                format!(
+                    "Health check answered {} in {:.1} ms",
+                    upstream_status, latency_ms
+                )
+                .bright_black()
+            );
+        }
+        Ok(())
+    }
+
+    /// Checks that the daemon is up and tests every registered backend
+    /// through `POST /test`, in registry order.
+    pub async fn run_doctor(&self) -> Result<Vec<BackendCheck>, ClientError> {
+        self.check_server_status().await?;
+        let url = format!("{}/test", self.base_url);
+        let mut checks = Vec::new();
+        for server in self.list_servers(None).await? {
+            let response = self
+                .admin_post(&url)
+                .json(&TestRequest {
+                    addr: server.addr.clone(),
+                })
+                .send()
+                .await?;
+            // Failed probes still come with a `TestResult`, anything else is
+            // a problem with the daemon
+            let status = response.status();
+            let result = match response.json::<TestResult>().await {
+                Ok(result) => result,
+                Err(_) => {
+                    return Err(ClientError::Server {
+                        status,
+                        message: format!("Unexpected response testing {}", server.addr),
+                    })
+                }
+            };
+            checks.push(BackendCheck { server, result });
+        }
+        Ok(checks)
+    }
+
+    /// Prints a table of which registered backends are reachable, failing if
+    /// any of them isn't.
+    pub async fn doctor(&self) -> Result<(), ClientError> {
+        let checks = self.run_doctor().await?;
+        println!(
+            "{} {}",
+            "✔".green().bold(),
+            format!("llmproxyd is up at {}", self.base_url).green()
+        );
+        if checks.is_empty() {
+            println!(
+                "{} {}",
+                "ℹ".bright_blue().bold(),
+                "No model services are currently registered".bright_black()
+            );
+            return Ok(());
+        }
+
+        let model_width = checks
+            .iter()
+            .map(|check| check.server.model_names.join(", ").len())
+            .max()
+            .unwrap_or_default()
+            .max(5);
+        let addr_width = checks
+            .iter()
+            .map(|check| check.server.addr.len())
+            .max()
+            .unwrap_or_default()
+            .max(7);
+        println!();
+        println!(
+            "{:<5}  {:<model_width$}  {:<addr_width$}  {:<11}  {:>10}  Details",
+            "Label", "Model", "Address", "Status", "Latency",
+        );
+        for check in &checks {
+            let status = if check.reachable() {
+                format!("{:<11}", "reachable").green()
+            } else {
+                format!("{:<11}", "unreachable").red().bold()
+            };
+            let latency = check
+                .result
+                .latency_ms
+                .map(|latency_ms| format!("{:.1} ms", latency_ms))
+                .unwrap_or_else(|| "-".to_string());
+            println!(
+                "{:<5}  {:<model_width$}  {:<addr_width$}  {}  {:>10}  {}",
+                format!("#{}", check.server.index).bright_cyan(),
+                check.server.model_names.join(", "),
+                check.server.addr,
+                status,
+                latency,
+                check.result.message.bright_black(),
+            );
+        }
+
+        let unreachable = checks.iter().filter(|check| !check.reachable()).count();
+        println!();
+        if unreachable > 0 {
+            return Err(ClientError::Other(format!(
+                "{} of {} services are unreachable",
+                unreachable,
+                checks.len()
+            )));
+        }
+        println!(
+            "{} {}",
+            "✔".green().bold(),
+            format!("All {} services are reachable", checks.len()).green()
+        );
+        Ok(())
+    }
+
+    /// Sends `requests` minimal chat completions for `model` through the
+    /// proxy, `concurrency` at a time, and measures their latencies.
+    pub async fn run_bench(&self, model: &str, requests: usize, concurrency: usize) -> BenchReport {
+        let url = format!("{}/v1/chat/completions", self.base_url);
+        let body = serde_json::json!({
+            "model": model,
+            "messages": [{ "role": "user", "content": "ping" }],
+            "max_tokens": 1,
+        });
+        let next = Arc::new(AtomicUsize::new(0));
+        let started = Instant::now();
+
+        let workers: Vec<_> = (0..concurrency.clamp(1, requests.max(1)))
+            .map(|_| {
+                let http_client = self.http_client.clone();
+                let (url, body, next) = (url.clone(), body.clone(), next.clone());
+                tokio::spawn(async move {
+                    let mut latencies = Vec::new();
+                    let mut errors = 0;
+                    while next.fetch_add(1, Ordering::Relaxed) < requests {
+                        let sent = Instant::now();
+                        let succeeded = match http_client.post(&url).json(&body).send().await {
+                            // Includes reading the body, as a real client would
+                            Ok(response) if response.status().is_success() => {
+                                response.bytes().await.is_ok()
+                            }
+                            _ => false,
+                        };
+                        if succeeded {
+                            latencies.push(sent.elapsed());
+                        } else {
+                            errors += 1;
+                        }
+                    }
+                    (latencies, errors)
+                })
+            })
+            .collect();
+
+        let mut report = BenchReport {
+            latencies: Vec::with_capacity(requests),
+            errors: 0,
+            elapsed: Duration::ZERO,
+        };
+        for worker in workers {
+            let (latencies, errors) = worker.await.unwrap_or_default();
+            report.latencies.extend(latencies);
+            report.errors += errors;
+        }
+        report.elapsed = started.elapsed();
+        report.latencies.sort();
+        report
+    }
+
+    pub async fn bench(
+        &self,
+        model: &str,
+        requests: usize,
+        concurrency: usize,
+    ) -> Result<(), ClientError> {
+        self.check_server_status().await?;
+        let report = self.run_bench(model, requests, concurrency).await;
+
+        let summary = format!(
+            "{} requests in {:.2}s ({:.1} req/s), {} errors ({:.1}%)",
+            report.requests(),
+            report.elapsed.as_secs_f64(),
+            report.requests() as f64 / report.elapsed.as_secs_f64(),
+            report.errors,
+            report.error_rate() * 100.0
+        );
+        if report.errors == 0 {
+            println!("{} {}", "✔".green().bold(), summary.green());
+        } else {
+            println!("{} {}", "⚠".yellow().bold(), summary.yellow());
+        }
+        for p in [50.0, 90.0, 99.0] {
+            let latency = report
+                .percentile(p)
+                .map(|latency| format!("{:.1} ms", latency.as_secs_f64() * 1000.0))
+                .unwrap_or_else(|| "-".to_string());
+            println!("  p{:<3} {:>10}", p, latency);
+        }
+        Ok(())
+    }
+}
+
+/// Keeps a backend registered with llmproxyd from the backend's own process.
+///
+/// The registration is re-sent on every heartbeat. Registering is idempotent,
+/// so this is a no-op while the daemon knows the backend, and re-registers it
+/// when the daemon was restarted and lost its registry.
+pub struct Registrar {
+    client: Client,
+    request: RegisterRequest,
+    heartbeat_interval: Duration,
+}
+
+impl Registrar {
+    pub fn new(client: Client, request: RegisterRequest) -> Self {
+        Self {
+            client,
+            request,
+            heartbeat_interval: Duration::from_secs(30),
+        }
+    }
+
+    /// Sets how often the registration is refreshed (default: 30s).
+    pub fn with_heartbeat_interval(mut self, interval: Duration) -> Self {
+        self.heartbeat_interval = interval;
+        self
+    }
+
+    /// Registers once, failing if the daemon rejects or can't be reached, then
+    /// keeps heartbeating in a background task until the handle is stopped.
+    pub async fn start(self) -> Result<RegistrarHandle, ClientError> {
+        self.client.register_quiet(&self.request).await?;
+        tracing::info!(
+            "Registered {} at {}",
+            self.request.names().join(", "),
+            self.request.addr
+        );
+
+        let task = tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(self.heartbeat_interval);
+            // The first tick fires immediately and the registration just happened
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                match self.client.register_quiet(&self.request).await {
+                    Ok(true) => tracing::warn!(
+                        "llmproxyd had lost the registration of {} (restarted?), registered again",
+                        self.request.addr
+                    ),
+                    Ok(false) => tracing::trace!("Heartbeat for {} sent", self.request.addr),
+                    Err(e) => tracing::warn!("Heartbeat for {} failed: {}", self.request.addr, e),
+                }
+            }
+        });
+        Ok(RegistrarHandle { task })
+    }
+}
+
+/// Handle to a running [`Registrar`]; heartbeats stop when it is stopped or
+/// dropped.
+pub struct RegistrarHandle {
+    task: tokio::task::JoinHandle<()>,
+}
+
+impl RegistrarHandle {
+    pub fn stop(self) {
+        self.task.abort();
+    }
+}
+
+impl Drop for RegistrarHandle {
+    fn drop(&mut self) {
+        self.task.abort();
     }
 }
 
 async fn handle_response(
     response: reqwest::Response,
     context: Option<&str>,
-) -> Result<(), Box<dyn std::error::Error>> {
+) -> Result<(), ClientError> {
     let status = response.status();
     let parsed_response: ServerResponse = response.json().await?;
 
@@ -265,8 +1071,13 @@ async fn handle_response(
 async fn handle_error_response(
     status: StatusCode,
     response: reqwest::Response,
-) -> Result<(), Box<dyn std::error::Error>> {
+) -> Result<(), ClientError> {
     let error_text = response.text().await?;
+    print_error_status(status, &error_text);
+    Ok(())
+}
+
+fn print_error_status(status: StatusCode, error_text: &str) {
     println!(
         "✖ {} ({})",
         format!("Server error: {}", error_text).red().bold(),
@@ -286,6 +1097,450 @@ async fn handle_error_response(
     } else if status.is_client_error() {
         println!("  {} Check your request parameters", "→".bright_blue());
     }
+}
 
-    Ok(())
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use httptest::{
+        all_of,
+        matchers::{eq, json_decoded, request},
+        responders, Expectation,
+    };
+
+    #[tokio::test]
+    async fn test_reqwest_errors_map_to_client_errors() {
+        // Nothing listens on a port that was just freed
+        let addr = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let client = Client::new(format!("http://{addr}"));
+        assert!(matches!(
+            client.list_servers(None).await,
+            Err(ClientError::ServerUnreachable(_))
+        ));
+
+        let server = httptest::Server::run();
+        server.expect(
+            Expectation::matching(request::method_path("GET", "/slow")).respond_with(
+                responders::delay_and_then(Duration::from_secs(5), responders::status_code(200)),
+            ),
+        );
+        let http_client = ReqwestClient::builder()
+            .timeout(Duration::from_millis(50))
+            .build()
+            .unwrap();
+        let e = http_client
+            .get(server.url_str("/slow"))
+            .send()
+            .await
+            .unwrap_err();
+        assert!(matches!(ClientError::from(e), ClientError::Timeout(_)));
+
+        server.expect(
+            Expectation::matching(request::method_path("GET", "/list"))
+                .respond_with(responders::status_code(200).body("<html>not json</html>")),
+        );
+        let client = Client::new(server.url_str("").trim_end_matches('/').to_string());
+        assert!(matches!(
+            client.list_servers(None).await,
+            Err(ClientError::InvalidResponse(_))
+        ));
+
+        server.expect(
+            Expectation::matching(request::method_path("GET", "/error"))
+                .respond_with(responders::status_code(500)),
+        );
+        let e = reqwest::get(server.url_str("/error"))
+            .await
+            .unwrap()
+            .error_for_status()
+            .unwrap_err();
+        assert!(matches!(
+            ClientError::from(e),
+            ClientError::Server {
+                status: StatusCode::INTERNAL_SERVER_ERROR,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_bench_percentiles() {
+        let latencies = (1..=100).map(Duration::from_millis).collect();
+        let report = BenchReport {
+            latencies,
+            errors: 25,
+            elapsed: Duration::from_secs(1),
+        };
+        assert_eq!(report.percentile(50.0), Some(Duration::from_millis(50)));
+        assert_eq!(report.percentile(90.0), Some(Duration::from_millis(90)));
+        assert_eq!(report.percentile(99.0), Some(Duration::from_millis(99)));
+        assert_eq!(report.percentile(100.0), Some(Duration::from_millis(100)));
+        assert_eq!(report.percentile(0.0), Some(Duration::from_millis(1)));
+        assert_eq!(report.requests(), 125);
+        assert!((report.error_rate() - 0.2).abs() < 1e-9);
+
+        let few = [Duration::from_millis(10), Duration::from_millis(20)];
+        assert_eq!(percentile(&few, 50.0), Some(Duration::from_millis(10)));
+        assert_eq!(percentile(&few, 99.0), Some(Duration::from_millis(20)));
+        assert_eq!(percentile(&[], 50.0), None);
+    }
+
+    #[tokio::test]
+    async fn test_run_bench_counts_errors() {
+        let server = httptest::Server::run();
+        server.expect(
+            Expectation::matching(request::method_path("POST", "/v1/chat/completions"))
+                .times(10)
+                .respond_with(httptest::cycle![
+                    responders::status_code(200),
+                    responders::status_code(200),
+                    responders::status_code(200),
+                    responders::status_code(200),
+                    responders::status_code(503),
+                ]),
+        );
+
+        let client = Client::new(server.url_str("").trim_end_matches('/').to_string());
+        let report = client.run_bench("test_model", 10, 3).await;
+        assert_eq!(report.requests(), 10);
+        assert_eq!(report.errors, 2);
+        assert_eq!(report.latencies.len(), 8);
+    }
+
+    #[tokio::test]
+    async fn test_run_doctor_reports_reachable_and_unreachable_backends() {
+        let daemon = httptest::Server::run();
+        daemon.expect(
+            Expectation::matching(request::method_path("GET", "/health"))
+                .times(2)
+                .respond_with(responders::status_code(200)),
+        );
+        daemon.expect(
+            Expectation::matching(request::method_path("GET", "/list"))
+                .times(2)
+                .respond_with(responders::json_encoded(vec![
+                    server_info("test_model", "localhost:8001", true),
+                    server_info("test_model", "localhost:8002", true),
+                ])),
+        );
+        daemon.expect(
+            Expectation::matching(all_of![
+                request::method_path("POST", "/test"),
+                request::body(json_decoded(eq(
+                    serde_json::json!({"addr": "localhost:8001"})
+                ))),
+            ])
+            .times(2)
+            .respond_with(responders::json_encoded(TestResult {
+                status: ResponseStatus::Success,
+                message: "Service at localhost:8001 is reachable".to_string(),
+                latency_ms: Some(1.5),
+                upstream_status: Some(200),
+            })),
+        );
+        daemon.expect(
+            Expectation::matching(all_of![
+                request::method_path("POST", "/test"),
+                request::body(json_decoded(eq(
+                    serde_json::json!({"addr": "localhost:8002"})
+                ))),
+            ])
+            .times(2)
+            .respond_with(
+                responders::status_code(502).body(
+                    serde_json::to_string(&TestResult {
+                        status: ResponseStatus::Error,
+                        message: "Connection refused".to_string(),
+                        latency_ms: None,
+                        upstream_status: None,
+                    })
+                    .unwrap(),
+                ),
+            ),
+        );
+
+        let client = Client::new(daemon.url_str("").trim_end_matches('/').to_string());
+        let checks = client.run_doctor().await.unwrap();
+        let outcomes: Vec<(&str, bool)> = checks
+            .iter()
+            .map(|check| (check.server.addr.as_str(), check.reachable()))
+            .collect();
+        assert_eq!(
+            outcomes,
+            vec![("localhost:8001", true), ("localhost:8002", false)]
+        );
+        assert!(client.doctor().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_run_doctor_fails_when_daemon_is_down() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        drop(listener);
+
+        let client = Client::new(format!("http://{addr}"));
+        let result = client.run_doctor().await;
+        assert!(matches!(result, Err(ClientError::ServerUnreachable(_))));
+    }
+
+    fn register_request(addr: &str) -> RegisterRequest {
+        RegisterRequest {
+            model_name: "test_model".to_string(),
+            model_names: Vec::new(),
+            addr: addr.to_string(),
+            addrs: Vec::new(),
+            pattern: false,
+            weight: None,
+            warmup_secs: None,
+            max_concurrency: None,
+            upstream_api_key: None,
+            capabilities: Vec::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_register_all_fails_if_any_entry_is_rejected() {
+        let daemon = httptest::Server::run();
+        daemon.expect(
+            Expectation::matching(request::method_path("GET", "/health"))
+                .times(1)
+                .respond_with(responders::status_code(200)),
+        );
+        let requests = vec![
+            register_request("localhost:8001"),
+            register_request("no-port"),
+        ];
+        daemon.expect(
+            Expectation::matching(all_of![
+                request::method_path("POST", "/register/batch"),
+                request::body(json_decoded(eq(serde_json::to_value(&requests).unwrap()))),
+            ])
+            .times(1)
+            .respond_with(responders::json_encoded(vec![
+                BatchRegisterResult {
+                    addr: "localhost:8001".to_string(),
+                    status: ResponseStatus::Success,
+                    message: "Server registered successfully".to_string(),
+                },
+                BatchRegisterResult {
+                    addr: "no-port".to_string(),
+                    status: ResponseStatus::Error,
+                    message: "Invalid address format. Expected host:port".to_string(),
+                },
+            ])),
+        );
+
+        let client = Client::new(daemon.url_str("").trim_end_matches('/').to_string());
+        let Err(ClientError::Other(message)) = client.register_all(&requests).await else {
+            panic!("a rejected entry should fail the batch");
+        };
+        assert_eq!(message, "1 of 2 registrations were rejected");
+    }
+
+    #[tokio::test]
+    async fn test_registrar_registers_and_heartbeats() {
+        let daemon = httptest::Server::run();
+        daemon.expect(
+            Expectation::matching(request::method_path("POST", "/register"))
+                .times(3..)
+                .respond_with(responders::cycle![
+                    responders::status_code(201)
+                        .body(r#"{"status":"Success","message":"Server registered successfully"}"#),
+                    responders::status_code(200)
+                        .body(r#"{"status":"Warning","message":"Server already registered"}"#),
+                ]),
+        );
+
+        let client = Client::new(daemon.url_str("").trim_end_matches('/').to_string());
+        let handle = Registrar::new(client, register_request("localhost:8001"))
+            .with_heartbeat_interval(Duration::from_millis(50))
+            .start()
+            .await
+            .unwrap();
+
+        tokio::time::sleep(Duration::from_millis(180)).await;
+        handle.stop();
+    }
+
+    #[tokio::test]
+    async fn test_registrar_fails_when_registration_is_rejected() {
+        let daemon = httptest::Server::run();
+        daemon.expect(
+            Expectation::matching(request::method_path("POST", "/register"))
+                .times(1)
+                .respond_with(
+                    responders::status_code(401)
+                        .body(r#"{"status":"Error","message":"Unauthorized"}"#),
+                ),
+        );
+
+        let client = Client::new(daemon.url_str("").trim_end_matches('/').to_string());
+        let result = Registrar::new(client, register_request("localhost:8001"))
+            .start()
+            .await;
+        assert!(result.is_err());
+    }
+
+    fn server_info(model_name: &str, addr: &str, healthy: bool) -> ProxyServerInfo {
+        ProxyServerInfo {
+            index: 1,
+            model_name: model_name.to_string(),
+            model_names: vec![model_name.to_string()],
+            addr: addr.to_string(),
+            addrs: Vec::new(),
+            pattern: false,
+            weight: 1,
+            scheme: "http".to_string(),
+            traffic_percent: 50.0,
+            circuit: "closed".to_string(),
+            healthy,
+            warming_up: false,
+            draining: false,
+            in_flight: 0,
+            total_served: 0,
+            capabilities: Vec::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_wait_ready_returns_once_enough_backends_are_healthy() {
+        let daemon = httptest::Server::run();
+        daemon.expect(
+            Expectation::matching(request::method_path("GET", "/list"))
+                .times(3)
+                .respond_with(responders::cycle![
+                    responders::json_encoded(Vec::<ProxyServerInfo>::new()),
+                    responders::json_encoded(vec![
+                        server_info("test_model", "localhost:8001", true),
+                        server_info("test_model", "localhost:8002", false),
+                    ]),
+                    responders::json_encoded(vec![
+                        server_info("test_model", "localhost:8001", true),
+                        server_info("test_model", "localhost:8002", true),
+                        server_info("other_model", "localhost:8003", true),
+                    ]),
+                ]),
+        );
+
+        let client = Client::new(daemon.url_str("").trim_end_matches('/').to_string());
+        client
+            .wait_ready("test_model", 2, Duration::from_secs(10))
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_wait_ready_times_out() {
+        let daemon = httptest::Server::run();
+        daemon.expect(
+            Expectation::matching(request::method_path("GET", "/list"))
+                .times(1..)
+                .respond_with(responders::json_encoded(vec![server_info(
+                    "test_model",
+                    "localhost:8001",
+                    true,
+                )])),
+        );
+
+        let client = Client::new(daemon.url_str("").trim_end_matches('/').to_string());
+        let result = client
+            .wait_ready("test_model", 2, Duration::from_secs(1))
+            .await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_list_servers_empty_registry_is_ok() {
+        let daemon = httptest::Server::run();
+        daemon.expect(
+            Expectation::matching(request::method_path("GET", "/list"))
+                .times(1)
+                .respond_with(responders::status_code(200).body("[]")),
+        );
+
+        let client = Client::new(daemon.url_str("").trim_end_matches('/').to_string());
+        assert!(client.list_servers(None).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_list_servers_encodes_model_name() {
+        use httptest::matchers::*;
+
+        let daemon = httptest::Server::run();
+        daemon.expect(
+            Expectation::matching(all_of![
+                request::method_path("GET", "/list"),
+                request::query(url_decoded(contains(("model_name", "org/my model:通义")))),
+            ])
+            .times(1)
+            .respond_with(responders::status_code(200).body("[]")),
+        );
+
+        let client = Client::new(daemon.url_str("").trim_end_matches('/').to_string());
+        assert!(client
+            .list_servers(Some("org/my model:通义"))
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_list_servers_page_reads_total() {
+        use httptest::matchers::*;
+
+        let daemon = httptest::Server::run();
+        daemon.expect(
+            Expectation::matching(all_of![
+                request::method_path("GET", "/list"),
+                request::query(url_decoded(contains(("offset", "2")))),
+                request::query(url_decoded(contains(("limit", "1")))),
+            ])
+            .times(1)
+            .respond_with(
+                responders::status_code(200)
+                    .insert_header(TOTAL_COUNT_HEADER, "3")
+                    .body(r#"[{"index":3,"model_name":"m","addr":"localhost:8003"}]"#),
+            ),
+        );
+
+        let client = Client::new(daemon.url_str("").trim_end_matches('/').to_string());
+        let page = client
+            .list_servers_page(ListQuery {
+                model_name: None,
+                offset: Some(2),
+                limit: Some(1),
+            })
+            .await
+            .unwrap();
+        assert_eq!(page.total, 3);
+        assert_eq!(page.servers.len(), 1);
+        assert_eq!(page.servers[0].index, 3);
+        // An older daemon's single model name is carried over
+        assert_eq!(page.servers[0].model_names, ["m"]);
+    }
+
+    #[tokio::test]
+    async fn test_list_servers_reports_error_status() {
+        let daemon = httptest::Server::run();
+        daemon.expect(
+            Expectation::matching(request::method_path("GET", "/list"))
+                .times(1)
+                .respond_with(
+                    responders::status_code(503)
+                        .body(r#"{"status":"Error","message":"Down for maintenance"}"#),
+                ),
+        );
+
+        let client = Client::new(daemon.url_str("").trim_end_matches('/').to_string());
+        match client.list_servers(None).await {
+            Err(ClientError::Server { status, message }) => {
+                assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+                assert_eq!(message, "Down for maintenance");
+            }
+            other => panic!("expected a status error, got {:?}", other),
+        }
+    }
 }
@@ -0,0 +1,115 @@
+//! Configuration file read by `llmproxyd --config`.
+//!
+//! The file is JSON. Every field is optional, and flags given on the command
+//! line take precedence over the values in the file:
+//!
+//! ```json
+//! {
+//!     "host": "127.0.0.1",
+//!     "port": 11450,
+//!     "strategy": "least-connections",
+//!     "upstream_timeout": 600,
+//!     "path_prefixes": { "/qwen": "Qwen/Qwen2-7B-Instruct" },
+//!     "servers": [
+//!         { "model_name": "Qwen/Qwen2-7B-Instruct", "addr": "127.0.0.1:8001", "weight": 2 },
+//!         { "model_name": "Qwen/Qwen2-7B-Instruct", "addr": "127.0.0.1:8002" }
+//!     ]
+//! }
+//! ```
+
+use crate::models::RegisterRequest;
+use crate::server::Strategy;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+use std::path::Path;
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigFile {
+    pub host: Option<IpAddr>,
+    pub port: Option<u16>,
+    pub strategy: Option<Strategy>,
+    /// Seconds to wait for a backend response.
+    pub upstream_timeout: Option<u64>,
+    /// Seconds a streaming response may wait on a client that stopped reading.
+    pub client_idle_timeout: Option<u64>,
+    /// Backends registered at startup, with the same fields as `POST /register`.
+    #[serde(default)]
+    pub servers: Vec<RegisterRequest>,
+    /// Path prefixes mapped to the model that requests under them are routed
+    /// to, e.g. `/qwen/v1/chat/completions` with `{"/qwen": "qwen"}`.
+    #[serde(default)]
+    pub path_prefixes: BTreeMap<String, String>,
+}
+
+impl ConfigFile {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let contents = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let config: Self = serde_json::from_str(&contents).map_err(|e| e.to_string())?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        for prefix in self.path_prefixes.keys() {
+            if !prefix.starts_with('/') || prefix.ends_with('/') {
+                return Err(format!(
+                    "Invalid path prefix {prefix:?}: must start with '/' and not end with one"
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_config_file() {
+        let config: ConfigFile = serde_json::from_str(
+            r#"{
+                "port": 8080,
+                "strategy": "round-robin",
+                "servers": [
+                    { "model_name": "a", "addr": "127.0.0.1:8001", "weight": 3 },
+                    { "model_name": "b", "addr": "https://example.com:443" }
+                ]
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(config.port, Some(8080));
+        assert_eq!(config.host, None);
+        assert_eq!(config.strategy, Some(Strategy::RoundRobin));
+        assert_eq!(config.servers.len(), 2);
+        assert_eq!(config.servers[0].weight, Some(3));
+        assert_eq!(config.servers[1].addr, "https://example.com:443");
+
+        let empty: ConfigFile = serde_json::from_str("{}").unwrap();
+        assert!(empty.servers.is_empty());
+
+        // Typos are reported rather than silently ignored
+        assert!(serde_json::from_str::<ConfigFile>(r#"{"prot": 8080}"#).is_err());
+        assert!(serde_json::from_str::<ConfigFile>(r#"{"strategy": "fastest"}"#).is_err());
+    }
+
+    #[test]
+    fn test_path_prefixes_are_validated() {
+        let parse = |json: &str| serde_json::from_str::<ConfigFile>(json).unwrap();
+        let config = parse(r#"{"path_prefixes": {"/qwen": "a", "/llama/v2": "b"}}"#);
+        assert!(config.validate().is_ok());
+        assert_eq!(config.path_prefixes["/qwen"], "a");
+
+        assert!(parse(r#"{"path_prefixes": {"qwen": "a"}}"#)
+            .validate()
+            .is_err());
+        assert!(parse(r#"{"path_prefixes": {"/qwen/": "a"}}"#)
+            .validate()
+            .is_err());
+        assert!(parse(r#"{"path_prefixes": {"/": "a"}}"#)
+            .validate()
+            .is_err());
+    }
+}
@@ -3,5 +3,6 @@
 //! This library contains the core logic for the llmproxy server and client.
 
 pub mod client;
+pub mod config;
 pub mod models;
 pub mod server;
@@ -1,11 +1,77 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
-/// Represents the payload for registering or unregistering a model server.
+/// Represents the payload for registering a model server.
 /// Used by both the client and the server.
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct RegisterRequest {
+    #[serde(default)]
     pub model_name: String,
+    /// Additional names served at the same address, e.g. several models
+    /// loaded by one vLLM process or a model plus its alias.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub model_names: Vec<String>,
+    /// `host:port`, optionally prefixed with `http://` or `https://`.
     pub addr: String,
+    /// Further addresses of the same server (e.g. a VIP), tried in order when
+    /// connecting to `addr` fails. They use the scheme of `addr`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub addrs: Vec<String>,
+    /// Treat `model_name` as a glob pattern (e.g. `Qwen/*`) instead of an exact name.
+    #[serde(default)]
+    pub pattern: bool,
+    /// Relative share of the model's traffic; defaults to 1.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub weight: Option<u32>,
+    /// Seconds after registration during which failed health checks are ignored.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub warmup_secs: Option<u64>,
+    /// Most requests this server handles at once; requests beyond it go to
+    /// other replicas.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_concurrency: Option<usize>,
+    /// Sent to the server as `Authorization: Bearer <key>` in place of the
+    /// client's credentials. Never shown by `/list`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub upstream_api_key: Option<String>,
+    /// Features the server supports (e.g. `vision`), which requests can
+    /// require with the `X-Require-Capability` header.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub capabilities: Vec<String>,
+}
+
+impl RegisterRequest {
+    /// All model names in the request, trimmed and without duplicates.
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = Vec::new();
+        for name in std::iter::once(&self.model_name).chain(&self.model_names) {
+            let name = name.trim();
+            if !name.is_empty() && !names.iter().any(|n| n == name) {
+                names.push(name.to_string());
+            }
+        }
+        names
+    }
+}
+
+/// Alternative model names clients may request, mapped to the registered
+/// model that serves them. Serialized as a plain JSON object, which is the
+/// format of the daemon's `--alias-file`.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(transparent)]
+pub struct ModelAliases(pub HashMap<String, String>);
+
+impl ModelAliases {
+    /// The model `name` is an alias for, if any.
+    pub fn resolve(&self, name: &str) -> Option<&str> {
+        self.0.get(name).map(String::as_str)
+    }
+}
+
+impl Extend<(String, String)> for ModelAliases {
+    fn extend<I: IntoIterator<Item = (String, String)>>(&mut self, iter: I) {
+        self.0.extend(iter)
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
@@ -23,6 +89,48 @@ pub struct ServerResponse {
     pub message: String,
 }
 
+/// Outcome of one entry of a `POST /register/batch`, in the order of the
+/// request's entries.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct BatchRegisterResult {
+    pub addr: String,
+    pub status: ResponseStatus,
+    pub message: String,
+}
+
+/// Error body in the format of the OpenAI API, returned by the proxy
+/// endpoints instead of a [`ServerResponse`] when `--openai-errors` is set.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct OpenAiError {
+    pub error: OpenAiErrorDetail,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct OpenAiErrorDetail {
+    pub message: String,
+    /// E.g. `invalid_request_error` or `server_error`.
+    #[serde(rename = "type")]
+    pub error_type: String,
+    pub param: Option<String>,
+    /// E.g. `model_not_found`, when the error has a specific cause.
+    pub code: Option<String>,
+}
+
+/// Query parameters of `GET /list`.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ListQuery {
+    /// Only list servers registered under this exact model name or pattern.
+    pub model_name: Option<String>,
+    /// Matching servers to skip.
+    pub offset: Option<usize>,
+    /// Most servers to list, or all of them if unset.
+    pub limit: Option<usize>,
+}
+
+/// Header of `GET /list` responses with the number of matching servers
+/// before `offset` and `limit` are applied.
+pub const TOTAL_COUNT_HEADER: &str = "x-total-count";
+
 /// Used by the server to extract the model name from the request body.
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ModelExtractPayload {
@@ -31,13 +139,183 @@ pub struct ModelExtractPayload {
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ProxyServerInfo {
+    /// 1-based position in the full registry, as accepted by `unregister`.
+    /// Unfiltered listings are in index order.
+    #[serde(default)]
+    pub index: usize,
+    /// The first of `model_names`, for consumers that predate it.
+    #[serde(default)]
     pub model_name: String,
+    #[serde(default)]
+    pub model_names: Vec<String>,
     pub addr: String,
+    /// Addresses tried in order when connecting to `addr` fails.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub addrs: Vec<String>,
+    #[serde(default)]
+    pub pattern: bool,
+    #[serde(default)]
+    pub weight: u32,
+    /// `http` or `https`.
+    #[serde(default)]
+    pub scheme: String,
+    /// `weight` as a percentage of the total weight registered for the first
+    /// of `model_names`.
+    #[serde(default)]
+    pub traffic_percent: f64,
+    /// Circuit breaker state: `closed`, `open` or `half-open`.
+    #[serde(default)]
+    pub circuit: String,
+    /// Whether the server is passing health checks and not tripped by the
+    /// circuit breaker.
+    #[serde(default = "default_healthy")]
+    pub healthy: bool,
+    /// Whether the server is in its warmup period, during which failed
+    /// health checks don't count against it.
+    #[serde(default)]
+    pub warming_up: bool,
+    /// Whether the server is draining: it finishes its requests in flight
+    /// but gets no new ones.
+    #[serde(default)]
+    pub draining: bool,
+    /// Requests currently in flight to the server.
+    #[serde(default)]
+    pub in_flight: usize,
+    /// Requests the server has finished since it was first used.
+    #[serde(default)]
+    pub total_served: u64,
+    /// Capabilities the server was registered with, sorted.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub capabilities: Vec<String>,
 }
 
+fn default_healthy() -> bool {
+    true
+}
 
 /// Represents the payload for testing a model server.
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct TestRequest {
     pub addr: String,
-}
\ No newline at end of file
+}
+
+/// Result of probing a backend through `POST /test`. A superset of
+/// [`ServerResponse`], so older clients can still read it.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TestResult {
+    pub status: ResponseStatus,
+    pub message: String,
+    /// Time until the backend answered the probe, in milliseconds.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<f64>,
+    /// Status code the backend answered the probe with.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub upstream_status: Option<u16>,
+}
+
+/// Response of `GET /stats`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Stats {
+    /// Proxied requests currently being handled.
+    pub in_flight: usize,
+    /// Requests in flight for longer than the daemon's threshold, longest
+    /// running first.
+    pub long_running: Vec<LongRunningRequest>,
+    /// Replica counts per registered model name or pattern, in registration
+    /// order.
+    #[serde(default)]
+    pub models: Vec<ModelStats>,
+}
+
+/// Query parameters of `GET /throughput`.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ThroughputQuery {
+    /// Seconds to look back; defaults to 60.
+    pub window: Option<u64>,
+}
+
+/// Response of `GET /throughput`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ThroughputStats {
+    pub window_secs: u64,
+    /// Models that answered requests within the window, by name.
+    pub models: Vec<ModelThroughput>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ModelThroughput {
+    pub model_name: String,
+    /// Requests answered by a backend within the window.
+    pub requests: u64,
+    pub requests_per_sec: f64,
+    /// Average time until the response headers arrived, including queueing
+    /// and retries.
+    pub avg_latency_ms: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ModelStats {
+    pub model_name: String,
+    pub replica_count: usize,
+    /// Replicas that are passing health checks and not tripped by the
+    /// circuit breaker.
+    pub healthy_count: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LongRunningRequest {
+    pub request_id: Option<String>,
+    pub model_name: String,
+    pub addr: String,
+    pub elapsed_secs: f64,
+}
+
+/// Payload of `POST /unregister`: the server at `addr`, or with an empty
+/// `addr` every server of `model_name`, or with `all` every server.
+///
+/// A [`RegisterRequest`] body is accepted too, as sent by older clients.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct UnregisterRequest {
+    #[serde(default)]
+    pub model_name: String,
+    #[serde(default)]
+    pub addr: String,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub all: bool,
+}
+
+/// Payload of `POST /drain`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DrainRequest {
+    pub addr: String,
+}
+
+/// Payload for switching the proxy into maintenance mode.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MaintenanceRequest {
+    /// Explanation returned to clients while in maintenance.
+    pub message: String,
+    /// Status code returned to clients; defaults to 503.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+}
+
+/// A single entry of the OpenAI-compatible `GET /v1/models` response.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ModelObject {
+    pub id: String,
+    pub object: String,
+    pub created: u64,
+    pub owned_by: String,
+    /// Number of backends registered for the model. Not part of the OpenAI
+    /// format, so only reported when the daemon is asked to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backends: Option<usize>,
+}
+
+/// The OpenAI-compatible `GET /v1/models` response.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ModelList {
+    pub object: String,
+    pub data: Vec<ModelObject>,
+}
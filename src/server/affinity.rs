@@ -0,0 +1,113 @@
+//! Session affinity learned from backends: a session sticks to the replica
+//! that marked its response with a cache header, rather than to one picked
+//! from a hash of the session id.
+
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// Sessions without a request for this long are forgotten.
+pub(crate) const SESSION_TTL: Duration = Duration::from_secs(30 * 60);
+
+/// Most sessions remembered at once; new sessions aren't pinned beyond it
+/// until older ones expire and are swept.
+pub(crate) const MAX_SESSIONS: usize = 100_000;
+
+/// How often expired sessions are forgotten.
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Debug)]
+struct Pin {
+    addr: String,
+    last_used: Instant,
+}
+
+/// Backend addresses by session id.
+#[derive(Debug, Default)]
+pub(crate) struct SessionAffinity {
+    sessions: Mutex<Sessions>,
+}
+
+#[derive(Debug, Default)]
+struct Sessions {
+    by_id: HashMap<Vec<u8>, Pin>,
+    /// `None` until the first session is pinned.
+    next_sweep: Option<Instant>,
+}
+
+impl SessionAffinity {
+    /// Pins `session` to `addr`, replacing any earlier pin.
+    pub(crate) fn record(&self, session: &[u8], addr: &str, now: Instant) {
+        let mut guard = self.sessions.lock().unwrap();
+        let sessions = &mut *guard;
+        if sessions.next_sweep.is_none_or(|at| now >= at) {
+            sessions
+                .by_id
+                .retain(|_, pin| now.saturating_duration_since(pin.last_used) < SESSION_TTL);
+            sessions.next_sweep = Some(now + SWEEP_INTERVAL);
+        }
+        if sessions.by_id.len() >= MAX_SESSIONS && !sessions.by_id.contains_key(session) {
+            tracing::debug!("Too many sessions, not pinning another one");
+            return;
+        }
+        sessions.by_id.insert(
+            session.to_vec(),
+            Pin {
+                addr: addr.to_string(),
+                last_used: now,
+            },
+        );
+    }
+
+    /// The address `session` is pinned to, if it was used recently.
+    pub(crate) fn get(&self, session: &[u8], now: Instant) -> Option<String> {
+        let sessions = &mut self.sessions.lock().unwrap().by_id;
+        let pin = sessions.get_mut(session)?;
+        if now.saturating_duration_since(pin.last_used) >= SESSION_TTL {
+            sessions.remove(session);
+            return None;
+        }
+        pin.last_used = now;
+        Some(pin.addr.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pin_is_replaced_and_expires() {
+        let affinity = SessionAffinity::default();
+        let start = Instant::now();
+        assert_eq!(affinity.get(b"s1", start), None);
+
+        affinity.record(b"s1", "a:1", start);
+        affinity.record(b"s1", "b:1", start);
+        assert_eq!(affinity.get(b"s1", start).as_deref(), Some("b:1"));
+
+        // Every use keeps the session alive
+        let later = start + SESSION_TTL - Duration::from_secs(1);
+        assert_eq!(affinity.get(b"s1", later).as_deref(), Some("b:1"));
+        assert_eq!(affinity.get(b"s1", later + SESSION_TTL), None);
+    }
+
+    #[test]
+    fn test_expired_sessions_are_swept() {
+        let affinity = SessionAffinity::default();
+        let start = Instant::now();
+        let second = Duration::from_secs(1);
+        affinity.record(b"s1", "a:1", start);
+        affinity.record(b"s2", "a:1", start + SESSION_TTL - second);
+        // Expired, but not yet due for a sweep
+        affinity.record(b"s3", "a:1", start + SESSION_TTL + second);
+        assert_eq!(affinity.sessions.lock().unwrap().by_id.len(), 3);
+
+        affinity.record(b"s4", "a:1", start + SESSION_TTL + SWEEP_INTERVAL);
+        let sessions = affinity.sessions.lock().unwrap();
+        assert!(!sessions.by_id.contains_key(b"s1".as_slice()));
+        assert_eq!(sessions.by_id.len(), 3);
+    }
+}
@@ -0,0 +1,85 @@
+//! Short cooldowns for backends that answered `429 Too Many Requests`, during
+//! which their replicas get the model's traffic instead.
+
+use axum::http::{header, HeaderMap};
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// Cooldown of a backend whose 429 didn't say when to come back.
+pub(crate) const DEFAULT_COOLDOWN: Duration = Duration::from_secs(5);
+
+/// Longest cooldown a `Retry-After` header can ask for, so that a backend
+/// can't take itself out of rotation for good.
+pub(crate) const MAX_COOLDOWN: Duration = Duration::from_secs(60);
+
+/// How long a 429 response asks the client to wait, from a `Retry-After`
+/// header in seconds. HTTP dates aren't supported.
+pub(crate) fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let secs: u64 = headers
+        .get(header::RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse()
+        .ok()?;
+    Some(Duration::from_secs(secs).min(MAX_COOLDOWN))
+}
+
+/// Backends backed off until some instant, by address.
+#[derive(Debug, Default)]
+pub(crate) struct Backoffs {
+    until: Mutex<HashMap<String, Instant>>,
+}
+
+impl Backoffs {
+    pub(crate) fn back_off(&self, addr: &str, cooldown: Duration, now: Instant) {
+        tracing::warn!("Backing off {} for {:?}", addr, cooldown);
+        self.until
+            .lock()
+            .unwrap()
+            .insert(addr.to_string(), now + cooldown);
+    }
+
+    pub(crate) fn is_backed_off(&self, addr: &str, now: Instant) -> bool {
+        let mut until = self.until.lock().unwrap();
+        match until.get(addr) {
+            Some(&deadline) if now < deadline => true,
+            Some(_) => {
+                until.remove(addr);
+                false
+            }
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_after_is_parsed_and_capped() {
+        let headers = |value: &'static str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(header::RETRY_AFTER, value.parse().unwrap());
+            headers
+        };
+        assert_eq!(retry_after(&headers("3")), Some(Duration::from_secs(3)));
+        assert_eq!(retry_after(&headers("3600")), Some(MAX_COOLDOWN));
+        assert_eq!(retry_after(&headers("Wed, 21 Oct 2015 07:28:00 GMT")), None);
+        assert_eq!(retry_after(&HeaderMap::new()), None);
+    }
+
+    #[test]
+    fn test_backoff_expires() {
+        let backoffs = Backoffs::default();
+        let start = Instant::now();
+        backoffs.back_off("a:1", Duration::from_secs(2), start);
+        assert!(backoffs.is_backed_off("a:1", start + Duration::from_secs(1)));
+        assert!(!backoffs.is_backed_off("b:1", start));
+        assert!(!backoffs.is_backed_off("a:1", start + Duration::from_secs(2)));
+    }
+}
@@ -0,0 +1,153 @@
+//! Optional in-memory cache of deterministic completions, for clients that
+//! replay identical `temperature: 0` requests.
+
+use axum::{
+    body::Bytes,
+    http::{HeaderMap, StatusCode},
+};
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, Mutex},
+};
+
+/// Responses with larger bodies are passed through without being cached.
+pub(crate) const MAX_CACHED_BODY_BYTES: u64 = 1024 * 1024;
+
+#[derive(Clone, Debug)]
+pub(crate) struct CachedResponse {
+    pub(crate) status: StatusCode,
+    pub(crate) headers: HeaderMap,
+    pub(crate) body: Bytes,
+}
+
+/// Key under which the response to a request body is cached, or `None` if
+/// the request may not get the same answer twice: streaming requests and
+/// sampling with a temperature other than 0 (which is also the case when it
+/// isn't set) bypass the cache.
+pub(crate) fn cache_key(model_name: &str, path_and_query: &str, body: &[u8]) -> Option<String> {
+    let body: serde_json::Value = serde_json::from_slice(body).ok()?;
+    let object = body.as_object()?;
+    if object.get("stream").and_then(serde_json::Value::as_bool) == Some(true) {
+        return None;
+    }
+    if object
+        .get("temperature")
+        .and_then(serde_json::Value::as_f64)
+        != Some(0.0)
+    {
+        return None;
+    }
+
+    // Object keys are serialized sorted, so formatting and key order don't
+    // change the key. The whole request is the key rather than a hash of it,
+    // so that different requests can never share a response.
+    Some(serde_json::json!([model_name, path_and_query, body]).to_string())
+}
+
+#[derive(Debug, Default)]
+struct Entries {
+    responses: HashMap<Arc<str>, (u64, CachedResponse)>,
+    /// Keys by the tick they were last used at, least recently used first.
+    by_use: BTreeMap<u64, Arc<str>>,
+    tick: u64,
+}
+
+/// Least recently used cache of up to `capacity` responses.
+#[derive(Debug)]
+pub(crate) struct ResponseCache {
+    capacity: usize,
+    entries: Mutex<Entries>,
+}
+
+impl ResponseCache {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: Mutex::new(Entries::default()),
+        }
+    }
+
+    pub(crate) fn get(&self, key: &str) -> Option<CachedResponse> {
+        let mut guard = self.entries.lock().unwrap();
+        let entries = &mut *guard;
+        entries.tick += 1;
+        let tick = entries.tick;
+        let (last_used, response) = entries.responses.get_mut(key)?;
+        let previous = std::mem::replace(last_used, tick);
+        let response = response.clone();
+        if let Some(key) = entries.by_use.remove(&previous) {
+            entries.by_use.insert(tick, key);
+        }
+        Some(response)
+    }
+
+    pub(crate) fn insert(&self, key: &str, response: CachedResponse) {
+        if self.capacity == 0 {
+            return;
+        }
+        let key: Arc<str> = Arc::from(key);
+        let mut entries = self.entries.lock().unwrap();
+        entries.tick += 1;
+        let tick = entries.tick;
+        if let Some((previous, _)) = entries.responses.insert(key.clone(), (tick, response)) {
+            entries.by_use.remove(&previous);
+        }
+        entries.by_use.insert(tick, key);
+        while entries.responses.len() > self.capacity {
+            let Some((_, oldest)) = entries.by_use.pop_first() else {
+                break;
+            };
+            entries.responses.remove(&oldest);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(body: &'static str) -> CachedResponse {
+        CachedResponse {
+            status: StatusCode::OK,
+            headers: HeaderMap::new(),
+            body: Bytes::from_static(body.as_bytes()),
+        }
+    }
+
+    #[test]
+    fn test_cache_key_only_for_deterministic_requests() {
+        let path = "/v1/chat/completions";
+        let key = |body: &str| cache_key("m", path, body.as_bytes());
+
+        assert!(key(r#"{"model":"m","temperature":0}"#).is_some());
+        assert_eq!(
+            key(r#"{"model":"m","temperature":0,"messages":[]}"#),
+            key(r#"{ "messages": [], "temperature": 0, "model": "m" }"#)
+        );
+        assert_ne!(
+            key(r#"{"model":"m","temperature":0}"#),
+            cache_key("m", "/v1/completions", br#"{"model":"m","temperature":0}"#)
+        );
+        assert_ne!(
+            key(r#"{"model":"m","temperature":0,"prompt":"a"}"#),
+            key(r#"{"model":"m","temperature":0,"prompt":"b"}"#)
+        );
+        assert!(key(r#"{"model":"m"}"#).is_none());
+        assert!(key(r#"{"model":"m","temperature":0.7}"#).is_none());
+        assert!(key(r#"{"model":"m","temperature":0,"stream":true}"#).is_none());
+        assert!(key("not json").is_none());
+    }
+
+    #[test]
+    fn test_least_recently_used_entry_is_evicted() {
+        let cache = ResponseCache::new(2);
+        cache.insert("1", response("one"));
+        cache.insert("2", response("two"));
+        assert!(cache.get("1").is_some());
+
+        cache.insert("3", response("three"));
+        assert!(cache.get("2").is_none());
+        assert_eq!(cache.get("1").unwrap().body, "one");
+        assert_eq!(cache.get("3").unwrap().body, "three");
+    }
+}
@@ -0,0 +1,218 @@
+//! Per-backend circuit breakers that temporarily take failing backends out of
+//! rotation.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// Failures older than this don't count towards tripping a circuit.
+const FAILURE_WINDOW: Duration = Duration::from_secs(60);
+
+/// Circuit state of a backend as reported by `/list`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum CircuitState {
+    /// Requests flow normally.
+    Closed,
+    /// Too many recent failures; the backend is skipped until the cooldown ends.
+    Open,
+    /// The cooldown ended and a single trial request decides the next state.
+    HalfOpen,
+}
+
+impl CircuitState {
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            Self::Closed => "closed",
+            Self::Open => "open",
+            Self::HalfOpen => "half-open",
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Circuit {
+    failures: VecDeque<Instant>,
+    /// Set while the circuit is open.
+    open_until: Option<Instant>,
+    /// Set while a half-open trial request is running.
+    trial_started: Option<Instant>,
+}
+
+impl Circuit {
+    fn state(&self, now: Instant) -> CircuitState {
+        match self.open_until {
+            Some(until) if now < until => CircuitState::Open,
+            Some(_) => CircuitState::HalfOpen,
+            None => CircuitState::Closed,
+        }
+    }
+}
+
+/// Circuit breakers for every backend address, created on first failure.
+#[derive(Debug)]
+pub(crate) struct CircuitBreakers {
+    /// Failures within [`FAILURE_WINDOW`] that open a circuit; 0 disables the
+    /// breakers.
+    threshold: u32,
+    cooldown: Duration,
+    circuits: Mutex<HashMap<String, Circuit>>,
+}
+
+impl CircuitBreakers {
+    pub(crate) fn new(threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            threshold,
+            cooldown,
+            circuits: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub(crate) fn state(&self, addr: &str, now: Instant) -> CircuitState {
+        self.circuits
+            .lock()
+            .unwrap()
+            .get(addr)
+            .map_or(CircuitState::Closed, |circuit| circuit.state(now))
+    }
+
+    /// Whether `addr` may be picked for a request.
+    pub(crate) fn is_available(&self, addr: &str, now: Instant) -> bool {
+        let circuits = self.circuits.lock().unwrap();
+        let Some(circuit) = circuits.get(addr) else {
+            return true;
+        };
+        match circuit.state(now) {
+            CircuitState::Closed => true,
+            CircuitState::Open => false,
+            CircuitState::HalfOpen => !self.trial_running(circuit, now),
+        }
+    }
+
+    /// Claims the request slot for `addr`, which for a half-open circuit is
+    /// the single trial request. Returns `false` if another request got it.
+    pub(crate) fn try_acquire(&self, addr: &str, now: Instant) -> bool {
+        let mut circuits = self.circuits.lock().unwrap();
+        let Some(circuit) = circuits.get_mut(addr) else {
+            return true;
+        };
+        match circuit.state(now) {
+            CircuitState::Closed => true,
+            CircuitState::Open => false,
+            CircuitState::HalfOpen => {
+                if self.trial_running(circuit, now) {
+                    return false;
+                }
+                circuit.trial_started = Some(now);
+                true
+            }
+        }
+    }
+
+    pub(crate) fn record_success(&self, addr: &str) {
+        if self.circuits.lock().unwrap().remove(addr).is_some() {
+            tracing::info!("Circuit for {} closed", addr);
+        }
+    }
+
+    pub(crate) fn record_failure(&self, addr: &str, now: Instant) {
+        if self.threshold == 0 {
+            return;
+        }
+
+        let mut circuits = self.circuits.lock().unwrap();
+        let circuit = circuits.entry(addr.to_string()).or_default();
+        match circuit.state(now) {
+            CircuitState::Closed => {
+                circuit.failures.push_back(now);
+                while circuit
+                    .failures
+                    .front()
+                    .is_some_and(|&failure| now.duration_since(failure) > FAILURE_WINDOW)
+                {
+                    circuit.failures.pop_front();
+                }
+                if circuit.failures.len() >= self.threshold as usize {
+                    tracing::warn!(
+                        "Opening circuit for {} after {} failures",
+                        addr,
+                        circuit.failures.len()
+                    );
+                    circuit.failures.clear();
+                    circuit.open_until = Some(now + self.cooldown);
+                }
+            }
+            // A failed trial (or a straggler from before the circuit opened)
+            // restarts the cooldown
+            CircuitState::HalfOpen | CircuitState::Open => {
+                tracing::warn!("Circuit for {} stays open", addr);
+                circuit.trial_started = None;
+                circuit.open_until = Some(now + self.cooldown);
+            }
+        }
+    }
+
+    /// A trial that has been running for a whole cooldown is assumed lost
+    /// (e.g. the client went away) so that the circuit can't get stuck.
+    fn trial_running(&self, circuit: &Circuit, now: Instant) -> bool {
+        circuit
+            .trial_started
+            .is_some_and(|started| now.duration_since(started) < self.cooldown)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_circuit_opens_and_recovers() {
+        let breakers = CircuitBreakers::new(2, Duration::from_secs(10));
+        let start = Instant::now();
+
+        breakers.record_failure("a:1", start);
+        assert!(breakers.is_available("a:1", start));
+        breakers.record_failure("a:1", start);
+        assert_eq!(breakers.state("a:1", start), CircuitState::Open);
+        assert!(!breakers.is_available("a:1", start + Duration::from_secs(5)));
+        assert!(breakers.is_available("b:1", start));
+
+        // After the cooldown exactly one trial request is let through
+        let later = start + Duration::from_secs(10);
+        assert_eq!(breakers.state("a:1", later), CircuitState::HalfOpen);
+        assert!(breakers.try_acquire("a:1", later));
+        assert!(!breakers.is_available("a:1", later));
+        assert!(!breakers.try_acquire("a:1", later));
+
+        breakers.record_success("a:1");
+        assert_eq!(breakers.state("a:1", later), CircuitState::Closed);
+        assert!(breakers.is_available("a:1", later));
+    }
+
+    #[test]
+    fn test_failed_trial_reopens_circuit() {
+        let breakers = CircuitBreakers::new(1, Duration::from_secs(10));
+        let start = Instant::now();
+        breakers.record_failure("a:1", start);
+
+        let later = start + Duration::from_secs(10);
+        assert!(breakers.try_acquire("a:1", later));
+        breakers.record_failure("a:1", later);
+        assert_eq!(breakers.state("a:1", later), CircuitState::Open);
+        assert!(!breakers.is_available("a:1", later + Duration::from_secs(9)));
+        assert!(breakers.is_available("a:1", later + Duration::from_secs(10)));
+    }
+
+    #[test]
+    fn test_old_failures_do_not_count() {
+        let breakers = CircuitBreakers::new(2, Duration::from_secs(10));
+        let start = Instant::now();
+        breakers.record_failure("a:1", start);
+        breakers.record_failure("a:1", start + FAILURE_WINDOW + Duration::from_secs(1));
+        assert_eq!(
+            breakers.state("a:1", start + FAILURE_WINDOW + Duration::from_secs(1)),
+            CircuitState::Closed
+        );
+    }
+}
@@ -0,0 +1,227 @@
+//! Active health checking of registered backends.
+
+use super::{upstream_authorization, upstream_uri, AppState, UpstreamScheme};
+use axum::http::{header, Request, StatusCode};
+use std::time::{Duration, Instant};
+
+/// Upper bound for a single health probe.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Wait before the first retry of a failed probe within a round, doubled for
+/// every further retry.
+const PROBE_RETRY_BACKOFF: Duration = Duration::from_millis(200);
+
+/// Probes every registered backend each `interval` until shutdown.
+pub(super) async fn run_health_checker(state: AppState, interval: Duration) {
+    let mut ticker = tokio::time::interval(interval);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    let shutdown = state.shutdown_requested();
+    tokio::pin!(shutdown);
+
+    loop {
+        tokio::select! {
+            _ = ticker.tick() => run_health_checks(&state).await,
+            _ = &mut shutdown => return,
+        }
+    }
+}
+
+/// Runs a single round of health checks, unregistering backends that have
+/// failed `health_check_failures` probes in a row outside their warmup. A
+/// backend with several addresses is healthy if any of them passes, and a
+/// failing backend is probed up to `health_check_retries` more times before
+/// the round counts it as failed.
+pub(super) async fn run_health_checks(state: &AppState) {
+    let mut targets: Vec<(UpstreamScheme, String, Vec<String>, Option<String>)> = Vec::new();
+    for server in state.servers.read().await.iter() {
+        if !targets.iter().any(|(_, addr, _, _)| *addr == server.addr) {
+            targets.push((
+                server.scheme,
+                server.addr.clone(),
+                server.addrs.clone(),
+                server.upstream_api_key.clone(),
+            ));
+        }
+    }
+
+    let mut results = Vec::with_capacity(targets.len());
+    for (scheme, addr, addrs, api_key) in targets {
+        let mut healthy = false;
+        let mut backoff = PROBE_RETRY_BACKOFF;
+        for attempt in 0..=state.config.health_check_retries {
+            if attempt > 0 {
+                tracing::debug!("Retrying health check for {} in {:?}", addr, backoff);
+                tokio::time::sleep(backoff).await;
+                backoff *= 2;
+            }
+            for probed in std::iter::once(&addr).chain(&addrs) {
+                healthy = probe_health(state, scheme, probed, api_key.as_deref())
+                    .await
+                    .is_ok_and(|probe| probe.status.is_success());
+                if healthy {
+                    break;
+                }
+            }
+            if healthy {
+                break;
+            }
+        }
+        results.push((addr, healthy));
+    }
+
+    let now = Instant::now();
+    // A threshold of 0 would evict healthy servers too
+    let threshold = state.config.health_check_failures.max(1);
+    let mut servers = state.servers.write().await;
+    for (addr, healthy) in results {
+        for server in servers.iter_mut().filter(|server| server.addr == addr) {
+            if healthy {
+                server.consecutive_failures = 0;
+                // A passing probe means the model has loaded
+                server.warmup_until = None;
+            } else if server.in_warmup(now) {
+                tracing::debug!("Ignoring failed health check for {} during warmup", addr);
+            } else {
+                server.consecutive_failures += 1;
+                tracing::warn!(
+                    "Health check failed for {} ({}/{})",
+                    addr,
+                    server.consecutive_failures,
+                    threshold
+                );
+            }
+        }
+    }
+
+    servers.retain(|server| {
+        let evict = server.consecutive_failures >= threshold;
+        if evict {
+            tracing::warn!(
+                "Unregistering unhealthy server: model_name={}, addr={}",
+                server.model_names.join(", "),
+                server.addr
+            );
+        }
+        !evict
+    });
+    state.forget_unregistered(&servers);
+}
+
+/// How a backend answered a health probe.
+#[derive(Debug)]
+pub(super) struct Probe {
+    pub(super) status: StatusCode,
+    pub(super) latency: Duration,
+}
+
+/// Sends `GET /health` to a backend, with its API key if it has one,
+/// returning an error description if it doesn't answer in time.
+pub(super) async fn probe_health(
+    state: &AppState,
+    scheme: UpstreamScheme,
+    addr: &str,
+    api_key: Option<&str>,
+) -> Result<Probe, String> {
+    let uri = upstream_uri(scheme, addr, "/health").map_err(|e| e.to_string())?;
+    let mut request = Request::get(uri);
+    if let Some(value) = api_key.and_then(upstream_authorization) {
+        request = request.header(header::AUTHORIZATION, value);
+    }
+    let request = request
+        .body(axum::body::Body::empty())
+        .map_err(|e| e.to_string())?;
+    let started = Instant::now();
+    match tokio::time::timeout(PROBE_TIMEOUT, state.http_client.request(request)).await {
+        Ok(Ok(response)) => Ok(Probe {
+            status: response.status(),
+            latency: started.elapsed(),
+        }),
+        Ok(Err(e)) => Err(e.to_string()),
+        Err(_) => Err(format!("timed out after {:?}", PROBE_TIMEOUT)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::{ProxyServer, ServerConfig};
+
+    #[tokio::test]
+    async fn test_failures_during_warmup_do_not_evict() {
+        let state = AppState::new(ServerConfig {
+            health_check_failures: 1,
+            ..Default::default()
+        });
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let dead_addr = listener.local_addr().unwrap().to_string();
+        drop(listener);
+
+        state.servers.write().await.push(ProxyServer {
+            warmup_until: Some(Instant::now() + Duration::from_secs(60)),
+            ..ProxyServer::new(vec!["test_model".to_string()], dead_addr)
+        });
+
+        run_health_checks(&state).await;
+        assert_eq!(state.servers.read().await.len(), 1);
+
+        // Once the warmup is over the same failure evicts the server
+        state.servers.write().await[0].warmup_until = Some(Instant::now());
+        run_health_checks(&state).await;
+        assert!(state.servers.read().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_healthy_server_is_kept() {
+        let backend = httptest::Server::run();
+        backend.expect(
+            httptest::Expectation::matching(httptest::matchers::request::method_path(
+                "GET", "/health",
+            ))
+            .times(2)
+            .respond_with(httptest::responders::status_code(200)),
+        );
+
+        let state = AppState::new(ServerConfig {
+            health_check_failures: 1,
+            ..Default::default()
+        });
+        state.servers.write().await.push(ProxyServer::new(
+            vec!["test_model".to_string()],
+            backend.addr().to_string(),
+        ));
+
+        run_health_checks(&state).await;
+        run_health_checks(&state).await;
+        assert_eq!(state.servers.read().await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_probe_that_recovers_within_the_round_is_not_a_failure() {
+        let backend = httptest::Server::run();
+        backend.expect(
+            httptest::Expectation::matching(httptest::matchers::request::method_path(
+                "GET", "/health",
+            ))
+            .times(2)
+            .respond_with(httptest::cycle![
+                httptest::responders::status_code(503),
+                httptest::responders::status_code(200),
+            ]),
+        );
+
+        let state = AppState::new(ServerConfig {
+            health_check_failures: 1,
+            health_check_retries: 1,
+            ..Default::default()
+        });
+        state.servers.write().await.push(ProxyServer::new(
+            vec!["test_model".to_string()],
+            backend.addr().to_string(),
+        ));
+
+        run_health_checks(&state).await;
+        let servers = state.servers.read().await;
+        assert_eq!(servers.len(), 1);
+        assert_eq!(servers[0].consecutive_failures, 0);
+    }
+}
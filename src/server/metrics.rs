@@ -0,0 +1,331 @@
+//! Prometheus metrics for the proxy, rendered in the text exposition format.
+
+use axum::http::StatusCode;
+use regex::Regex;
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
+
+/// Upper bounds (in seconds) of the upstream latency histogram buckets.
+const LATENCY_BUCKETS: [f64; 12] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 60.0,
+];
+
+#[derive(Debug, Default)]
+pub(crate) struct Metrics {
+    requests_total: AtomicU64,
+    request_body_buffered_bytes: AtomicU64,
+    malformed_responses: AtomicU64,
+    client_aborted: AtomicU64,
+    cache_hits: AtomicU64,
+    retries_granted: AtomicU64,
+    retries_denied: AtomicU64,
+    model_requests: Mutex<BTreeMap<String, u64>>,
+    backend_requests: Mutex<BTreeMap<String, u64>>,
+    responses_by_class: Mutex<BTreeMap<&'static str, u64>>,
+    upstream_latency: Histogram,
+    model_label: Option<Regex>,
+}
+
+impl Metrics {
+    pub(crate) fn new(model_label: Option<Regex>) -> Self {
+        Self {
+            model_label,
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn record_request(&self) {
+        self.requests_total.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_buffered_body(&self, bytes: usize) {
+        self.request_body_buffered_bytes
+            .fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_model(&self, model_name: &str) {
+        increment(&self.model_requests, self.model_label(model_name));
+    }
+
+    /// Label under which a model is counted, keeping the number of distinct
+    /// labels bounded when a `--metric-model-regex` is configured.
+    fn model_label<'a>(&self, model_name: &'a str) -> &'a str {
+        let Some(regex) = &self.model_label else {
+            return model_name;
+        };
+        match regex.captures(model_name) {
+            Some(captures) => captures
+                .get(1)
+                .or_else(|| captures.get(0))
+                .map_or(model_name, |m| m.as_str()),
+            None => "other",
+        }
+    }
+
+    pub(crate) fn record_backend(&self, addr: &str) {
+        increment(&self.backend_requests, addr);
+    }
+
+    pub(crate) fn record_malformed_response(&self) {
+        self.malformed_responses.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_client_aborted(&self) {
+        self.client_aborted.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_cache_hit(&self) {
+        self.cache_hits.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Counts a retry the retry budget allowed or refused.
+    pub(crate) fn record_retry_budget(&self, allowed: bool) {
+        let counter = if allowed {
+            &self.retries_granted
+        } else {
+            &self.retries_denied
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_response(&self, status: StatusCode) {
+        let class = match status.as_u16() {
+            100..=199 => "1xx",
+            200..=299 => "2xx",
+            300..=399 => "3xx",
+            400..=499 => "4xx",
+            _ => "5xx",
+        };
+        *self
+            .responses_by_class
+            .lock()
+            .unwrap()
+            .entry(class)
+            .or_default() += 1;
+    }
+
+    pub(crate) fn observe_upstream_latency(&self, latency: Duration) {
+        self.upstream_latency.observe(latency.as_secs_f64());
+    }
+
+    pub(crate) fn render(&self) -> String {
+        let mut out = String::new();
+
+        write_header(
+            &mut out,
+            "llmproxy_requests_total",
+            "counter",
+            "Total number of proxied requests.",
+        );
+        let _ = writeln!(
+            out,
+            "llmproxy_requests_total {}",
+            self.requests_total.load(Ordering::Relaxed)
+        );
+
+        write_header(
+            &mut out,
+            "llmproxy_request_body_buffered_bytes_total",
+            "counter",
+            "Request body bytes buffered in memory before forwarding.",
+        );
+        let _ = writeln!(
+            out,
+            "llmproxy_request_body_buffered_bytes_total {}",
+            self.request_body_buffered_bytes.load(Ordering::Relaxed)
+        );
+
+        write_header(
+            &mut out,
+            "llmproxy_upstream_malformed_responses_total",
+            "counter",
+            "Backend responses that could not be parsed as HTTP.",
+        );
+        let _ = writeln!(
+            out,
+            "llmproxy_upstream_malformed_responses_total {}",
+            self.malformed_responses.load(Ordering::Relaxed)
+        );
+
+        write_header(
+            &mut out,
+            "llmproxy_client_aborted_total",
+            "counter",
+            "Requests abandoned by the client before reaching a backend.",
+        );
+        let _ = writeln!(
+            out,
+            "llmproxy_client_aborted_total {}",
+            self.client_aborted.load(Ordering::Relaxed)
+        );
+
+        write_header(
+            &mut out,
+            "llmproxy_cache_hits_total",
+            "counter",
+            "Requests answered from the response cache.",
+        );
+        let _ = writeln!(
+            out,
+            "llmproxy_cache_hits_total {}",
+            self.cache_hits.load(Ordering::Relaxed)
+        );
+
+        write_header(
+            &mut out,
+            "llmproxy_retry_budget_granted_total",
+            "counter",
+            "Retries allowed by the retry budget.",
+        );
+        let _ = writeln!(
+            out,
+            "llmproxy_retry_budget_granted_total {}",
+            self.retries_granted.load(Ordering::Relaxed)
+        );
+
+        write_header(
+            &mut out,
+            "llmproxy_retry_budget_denied_total",
+            "counter",
+            "Retries refused because the retry budget was exhausted.",
+        );
+        let _ = writeln!(
+            out,
+            "llmproxy_retry_budget_denied_total {}",
+            self.retries_denied.load(Ordering::Relaxed)
+        );
+
+        write_header(
+            &mut out,
+            "llmproxy_model_requests_total",
+            "counter",
+            "Proxied requests per requested model.",
+        );
+        for (model, count) in self.model_requests.lock().unwrap().iter() {
+            let _ = writeln!(
+                out,
+                "llmproxy_model_requests_total{{model=\"{}\"}} {}",
+                escape_label(model),
+                count
+            );
+        }
+
+        write_header(
+            &mut out,
+            "llmproxy_backend_requests_total",
+            "counter",
+            "Requests forwarded to each backend.",
+        );
+        for (backend, count) in self.backend_requests.lock().unwrap().iter() {
+            let _ = writeln!(
+                out,
+                "llmproxy_backend_requests_total{{backend=\"{}\"}} {}",
+                escape_label(backend),
+                count
+            );
+        }
+
+        write_header(
+            &mut out,
+            "llmproxy_responses_total",
+            "counter",
+            "Responses returned to clients by status class.",
+        );
+        for (class, count) in self.responses_by_class.lock().unwrap().iter() {
+            let _ = writeln!(
+                out,
+                "llmproxy_responses_total{{class=\"{}\"}} {}",
+                class, count
+            );
+        }
+
+        write_header(
+            &mut out,
+            "llmproxy_upstream_latency_seconds",
+            "histogram",
+            "Time until the upstream returned response headers.",
+        );
+        self.upstream_latency
+            .render(&mut out, "llmproxy_upstream_latency_seconds");
+
+        out
+    }
+}
+
+#[derive(Debug)]
+struct Histogram {
+    /// Non-cumulative counts, one per bucket plus a trailing `+Inf` bucket.
+    buckets: Vec<AtomicU64>,
+    /// Sum of observations in microseconds.
+    sum_micros: AtomicU64,
+    count: AtomicU64,
+}
+
+impl Default for Histogram {
+    fn default() -> Self {
+        Self {
+            buckets: (0..=LATENCY_BUCKETS.len())
+                .map(|_| AtomicU64::new(0))
+                .collect(),
+            sum_micros: AtomicU64::new(0),
+            count: AtomicU64::new(0),
+        }
+    }
+}
+
+impl Histogram {
+    fn observe(&self, value: f64) {
+        let index = LATENCY_BUCKETS
+            .iter()
+            .position(|bound| value <= *bound)
+            .unwrap_or(LATENCY_BUCKETS.len());
+        self.buckets[index].fetch_add(1, Ordering::Relaxed);
+        self.sum_micros
+            .fetch_add((value * 1_000_000.0) as u64, Ordering::Relaxed);
+        self.count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn render(&self, out: &mut String, name: &str) {
+        let mut cumulative = 0;
+        for (index, bucket) in self.buckets.iter().enumerate() {
+            cumulative += bucket.load(Ordering::Relaxed);
+            let bound = LATENCY_BUCKETS
+                .get(index)
+                .map_or_else(|| "+Inf".to_string(), |bound| bound.to_string());
+            let _ = writeln!(out, "{name}_bucket{{le=\"{bound}\"}} {cumulative}");
+        }
+        let sum = self.sum_micros.load(Ordering::Relaxed) as f64 / 1_000_000.0;
+        let _ = writeln!(out, "{name}_sum {sum}");
+        let _ = writeln!(out, "{name}_count {}", self.count.load(Ordering::Relaxed));
+    }
+}
+
+fn increment(counters: &Mutex<BTreeMap<String, u64>>, key: &str) {
+    let mut counters = counters.lock().unwrap();
+    match counters.get_mut(key) {
+        Some(count) => *count += 1,
+        None => {
+            counters.insert(key.to_string(), 1);
+        }
+    }
+}
+
+fn write_header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} {kind}");
+}
+
+/// Escapes a value for use inside a quoted Prometheus label.
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
@@ -0,0 +1,88 @@
+//! Caps on the connections open to each backend, so that a backend that can
+//! only take so many isn't sent more and requests beyond the cap wait for a
+//! bounded time instead.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::{
+    sync::{OwnedSemaphorePermit, Semaphore},
+    time::error::Elapsed,
+};
+
+/// Connection slots per backend address. A request holds its slot until
+/// its response body is done, which is as long as an HTTP/1 connection is
+/// taken by it.
+#[derive(Debug)]
+pub(crate) struct ConnectionSlots {
+    limit: Option<usize>,
+    by_addr: Mutex<HashMap<String, Arc<Semaphore>>>,
+}
+
+impl ConnectionSlots {
+    pub(crate) fn new(limit: Option<usize>) -> Self {
+        Self {
+            limit,
+            by_addr: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Takes one of `addr`'s slots, waiting up to `timeout` (without one, as
+    /// long as it takes) for one to free up. Without a limit there are no
+    /// slots to take and this returns `Ok(None)` right away.
+    pub(crate) async fn acquire(
+        &self,
+        addr: &str,
+        timeout: Option<Duration>,
+    ) -> Result<Option<OwnedSemaphorePermit>, Elapsed> {
+        let Some(limit) = self.limit else {
+            return Ok(None);
+        };
+        let slots = self
+            .by_addr
+            .lock()
+            .unwrap()
+            .entry(addr.to_string())
+            .or_insert_with(|| Arc::new(Semaphore::new(limit)))
+            .clone();
+        let acquire = slots.acquire_owned();
+        let permit = match timeout {
+            Some(timeout) => tokio::time::timeout(timeout, acquire).await?,
+            None => acquire.await,
+        };
+        Ok(Some(permit.expect("the semaphore is never closed")))
+    }
+
+    /// Drops the slots of addresses for which `registered` is false and no
+    /// request holds a slot.
+    pub(crate) fn forget_unregistered(&self, registered: impl Fn(&str) -> bool) {
+        self.by_addr
+            .lock()
+            .unwrap()
+            .retain(|addr, slots| registered(addr) || Arc::strong_count(slots) > 1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_slots_are_capped_per_address() {
+        let timeout = Some(Duration::from_millis(50));
+        let slots = ConnectionSlots::new(Some(1));
+        let first = slots.acquire("a:1", timeout).await.unwrap();
+        assert!(first.is_some());
+        assert!(slots.acquire("a:1", timeout).await.is_err());
+        // Other backends have slots of their own
+        assert!(slots.acquire("b:1", timeout).await.unwrap().is_some());
+
+        drop(first);
+        assert!(slots.acquire("a:1", timeout).await.unwrap().is_some());
+
+        let unlimited = ConnectionSlots::new(None);
+        assert!(unlimited.acquire("a:1", timeout).await.unwrap().is_none());
+    }
+}
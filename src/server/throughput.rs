@@ -0,0 +1,146 @@
+//! Per-model request counts and latencies over the last few minutes, for
+//! autoscalers that need a current rate rather than cumulative counters.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// Longest window `GET /throughput` can report on, in seconds.
+pub(crate) const MAX_WINDOW_SECS: u64 = 300;
+
+/// Requests answered within some time span and their summed latency.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) struct Sample {
+    pub(crate) requests: u64,
+    pub(crate) latency: Duration,
+}
+
+impl Sample {
+    pub(crate) fn average_latency_ms(&self) -> f64 {
+        if self.requests == 0 {
+            0.0
+        } else {
+            self.latency.as_secs_f64() * 1000.0 / self.requests as f64
+        }
+    }
+}
+
+/// Samples of a single second.
+#[derive(Debug, Default)]
+struct Bucket {
+    /// Seconds since [`Throughput::started`] this bucket currently holds.
+    second: u64,
+    models: HashMap<String, Sample>,
+}
+
+/// Ring buffer of per-second buckets covering the last [`MAX_WINDOW_SECS`].
+#[derive(Debug)]
+pub(crate) struct Throughput {
+    started: Instant,
+    buckets: Mutex<Vec<Bucket>>,
+}
+
+impl Default for Throughput {
+    fn default() -> Self {
+        Self::new(Instant::now())
+    }
+}
+
+impl Throughput {
+    fn new(started: Instant) -> Self {
+        Self {
+            started,
+            buckets: Mutex::new((0..MAX_WINDOW_SECS).map(|_| Bucket::default()).collect()),
+        }
+    }
+
+    fn second(&self, now: Instant) -> u64 {
+        now.saturating_duration_since(self.started).as_secs()
+    }
+
+    /// Counts a request for `model_name` that got its response after
+    /// `latency`.
+    pub(crate) fn record(&self, model_name: &str, latency: Duration, now: Instant) {
+        let second = self.second(now);
+        let mut buckets = self.buckets.lock().unwrap();
+        let bucket = &mut buckets[(second % MAX_WINDOW_SECS) as usize];
+        if bucket.second != second {
+            // Left over from an earlier lap around the ring
+            bucket.second = second;
+            bucket.models.clear();
+        }
+        let sample = bucket.models.entry(model_name.to_string()).or_default();
+        sample.requests += 1;
+        sample.latency += latency;
+    }
+
+    /// Per-model totals over the last `window_secs` seconds, including the
+    /// current one. `window_secs` is capped at [`MAX_WINDOW_SECS`].
+    pub(crate) fn window(&self, window_secs: u64, now: Instant) -> BTreeMap<String, Sample> {
+        let current = self.second(now);
+        let window_secs = window_secs.min(MAX_WINDOW_SECS);
+        let mut totals: BTreeMap<String, Sample> = BTreeMap::new();
+        for bucket in self.buckets.lock().unwrap().iter() {
+            if bucket.second > current || current - bucket.second >= window_secs {
+                continue;
+            }
+            for (model_name, sample) in &bucket.models {
+                let total = totals.entry(model_name.clone()).or_default();
+                total.requests += sample.requests;
+                total.latency += sample.latency;
+            }
+        }
+        totals
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_window_counts_recent_requests_and_decays() {
+        let start = Instant::now();
+        let throughput = Throughput::new(start);
+        for _ in 0..4 {
+            throughput.record("a", Duration::from_millis(100), start);
+        }
+        throughput.record(
+            "a",
+            Duration::from_millis(300),
+            start + Duration::from_secs(5),
+        );
+        throughput.record(
+            "b",
+            Duration::from_millis(50),
+            start + Duration::from_secs(5),
+        );
+
+        let totals = throughput.window(10, start + Duration::from_secs(5));
+        assert_eq!(totals["a"].requests, 5);
+        assert!((totals["a"].average_latency_ms() - 140.0).abs() < 1e-6);
+        assert_eq!(totals["b"].requests, 1);
+
+        // The first burst has left a 5 second window
+        let totals = throughput.window(5, start + Duration::from_secs(5));
+        assert_eq!(totals["a"].requests, 1);
+
+        // And everything has left after a whole window without requests
+        let totals = throughput.window(10, start + Duration::from_secs(15));
+        assert!(totals.is_empty());
+    }
+
+    #[test]
+    fn test_buckets_are_reused_after_a_lap() {
+        let start = Instant::now();
+        let throughput = Throughput::new(start);
+        throughput.record("a", Duration::ZERO, start);
+        let lap = start + Duration::from_secs(MAX_WINDOW_SECS);
+        throughput.record("a", Duration::ZERO, lap);
+
+        let totals = throughput.window(MAX_WINDOW_SECS, lap);
+        assert_eq!(totals["a"].requests, 1);
+    }
+}
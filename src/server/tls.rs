@@ -0,0 +1,138 @@
+//! TLS termination on the proxy's own listeners, for exposing the daemon to
+//! clients over HTTPS without a reverse proxy in front of it.
+
+use super::AppState;
+use axum::{extract::ConnectInfo, Router};
+use hyper_util::{
+    rt::{TokioExecutor, TokioIo},
+    server::conn::auto,
+};
+use std::{fmt, net::SocketAddr, path::Path, sync::Arc, time::Duration};
+use tokio::{net::TcpListener, sync::mpsc};
+use tokio_rustls::{
+    rustls::{
+        self,
+        pki_types::{pem::PemObject, CertificateDer, PrivateKeyDer},
+    },
+    TlsAcceptor,
+};
+
+/// Connections that haven't completed the TLS handshake by then are closed,
+/// so that idle ones don't pile up or hold up shutdown.
+pub(super) const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long to pause accepting after an error, which is usually running out
+/// of file descriptors and would otherwise fail again right away.
+const ACCEPT_ERROR_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Certificate chain and private key the listeners present to clients.
+#[derive(Clone)]
+pub struct TlsConfig(Arc<rustls::ServerConfig>);
+
+impl fmt::Debug for TlsConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Keeps the key out of the logs
+        f.write_str("TlsConfig { .. }")
+    }
+}
+
+impl TlsConfig {
+    /// Loads a PEM certificate chain (leaf first) and the PEM private key
+    /// that belongs to it, failing if either can't be read or they don't
+    /// match.
+    pub fn load(cert_path: &Path, key_path: &Path) -> Result<Self, String> {
+        let certs = CertificateDer::pem_file_iter(cert_path)
+            .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+            .map_err(|e| format!("Failed to read certificate {}: {}", cert_path.display(), e))?;
+        if certs.is_empty() {
+            return Err(format!("No certificate found in {}", cert_path.display()));
+        }
+        let key = PrivateKeyDer::from_pem_file(key_path)
+            .map_err(|e| format!("Failed to read private key {}: {}", key_path.display(), e))?;
+
+        let mut config = rustls::ServerConfig::builder()
+            .with_no_client_auth()
+            .with_single_cert(certs, key)
+            .map_err(|e| format!("Invalid certificate or private key: {}", e))?;
+        config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+        Ok(Self(Arc::new(config)))
+    }
+}
+
+/// Serves `app` over TLS on `listener` until shutdown is requested, then
+/// waits for the open connections to finish their requests.
+pub(super) async fn serve_tls(
+    listener: TcpListener,
+    app: Router,
+    tls: TlsConfig,
+    tcp_nodelay: bool,
+    state: AppState,
+) {
+    let acceptor = TlsAcceptor::from(tls.0);
+    // Every connection holds a sender, so the channel closes once they are
+    // all done
+    let (open_tx, mut open_rx) = mpsc::channel::<()>(1);
+    let shutdown = state.shutdown_requested();
+    tokio::pin!(shutdown);
+
+    loop {
+        let (stream, remote_addr) = tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    tracing::warn!("Failed to accept connection: {}", e);
+                    tokio::time::sleep(ACCEPT_ERROR_BACKOFF).await;
+                    continue;
+                }
+            },
+            () = &mut shutdown => break,
+        };
+        if let Err(e) = stream.set_nodelay(tcp_nodelay) {
+            tracing::debug!("Failed to set TCP_NODELAY for {}: {}", remote_addr, e);
+        }
+
+        let acceptor = acceptor.clone();
+        let app = app.clone();
+        let connection_shutdown = state.shutdown_requested();
+        let open = open_tx.clone();
+        tokio::spawn(async move {
+            let _open = open;
+            let stream =
+                match tokio::time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await {
+                    Ok(Ok(stream)) => stream,
+                    Ok(Err(e)) => {
+                        tracing::debug!("TLS handshake with {} failed: {}", remote_addr, e);
+                        return;
+                    }
+                    Err(_) => {
+                        tracing::debug!("TLS handshake with {} timed out", remote_addr);
+                        return;
+                    }
+                };
+
+            let service = hyper::service::service_fn(move |mut request: hyper::Request<_>| {
+                request
+                    .extensions_mut()
+                    .insert(ConnectInfo::<SocketAddr>(remote_addr));
+                tower::Service::call(&mut app.clone(), request)
+            });
+            let builder = auto::Builder::new(TokioExecutor::new());
+            let connection = builder.serve_connection_with_upgrades(TokioIo::new(stream), service);
+            tokio::pin!(connection);
+            tokio::pin!(connection_shutdown);
+            let result = tokio::select! {
+                result = connection.as_mut() => result,
+                () = &mut connection_shutdown => {
+                    connection.as_mut().graceful_shutdown();
+                    connection.await
+                }
+            };
+            if let Err(e) = result {
+                tracing::debug!("Connection from {} failed: {}", remote_addr, e);
+            }
+        });
+    }
+
+    drop(open_tx);
+    let _ = open_rx.recv().await;
+}